@@ -0,0 +1,350 @@
+pub mod ps;
+
+use crate::commands::shorten_id;
+
+use std::any::Any;
+use std::collections::HashMap;
+
+use bollard::secret::{ContainerSummary, MountPoint, Port};
+use chrono::{DateTime, FixedOffset};
+use nu_protocol::{CustomValue, Filesize, Record, ShellError, Span, Value};
+use serde::{Deserialize, Serialize};
+
+/// A port published by a container.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContainerPort {
+    pub ip: String,
+    pub private_port: u16,
+    pub public_port: Option<u16>,
+    pub typ: String,
+}
+
+impl ContainerPort {
+    pub fn new(port: Port) -> Self {
+        Self {
+            ip: port.ip.unwrap_or_default(),
+            private_port: port.private_port,
+            public_port: port.public_port,
+            typ: port.typ.map(|t| t.to_string()).unwrap_or_default(),
+        }
+    }
+
+    pub fn to_value(&self, span: Span) -> Value {
+        let mut record = Record::new();
+        record.insert("ip".to_string(), Value::string(&self.ip, span));
+        record.insert(
+            "private_port".to_string(),
+            Value::int(self.private_port as i64, span),
+        );
+        record.insert(
+            "public_port".to_string(),
+            match self.public_port {
+                Some(port) => Value::int(port as i64, span),
+                None => Value::nothing(span),
+            },
+        );
+        record.insert("type".to_string(), Value::string(&self.typ, span));
+        Value::record(record, span)
+    }
+}
+
+/// A volume or bind mount of a container.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContainerMount {
+    pub typ: String,
+    pub name: String,
+    pub source: String,
+    pub destination: String,
+    pub mode: String,
+    pub rw: bool,
+}
+
+impl ContainerMount {
+    pub fn new(mount: MountPoint) -> Self {
+        Self {
+            typ: mount.typ.map(|t| t.to_string()).unwrap_or_default(),
+            name: mount.name.unwrap_or_default(),
+            source: mount.source.unwrap_or_default(),
+            destination: mount.destination.unwrap_or_default(),
+            mode: mount.mode.unwrap_or_default(),
+            rw: mount.rw.unwrap_or_default(),
+        }
+    }
+
+    pub fn to_value(&self, span: Span) -> Value {
+        let mut record = Record::new();
+        record.insert("type".to_string(), Value::string(&self.typ, span));
+        record.insert("name".to_string(), Value::string(&self.name, span));
+        record.insert("source".to_string(), Value::string(&self.source, span));
+        record.insert(
+            "destination".to_string(),
+            Value::string(&self.destination, span),
+        );
+        record.insert("mode".to_string(), Value::string(&self.mode, span));
+        record.insert("rw".to_string(), Value::bool(self.rw, span));
+        Value::record(record, span)
+    }
+}
+
+/// This struct contains the information about a container.
+/// It is also a custom value that can be used in NuShell.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Container {
+    pub id: String,
+    pub names: Vec<String>,
+    pub image: String,
+    pub image_id: String,
+    pub command: String,
+    pub created: DateTime<FixedOffset>,
+    pub ports: Vec<ContainerPort>,
+    pub size_rw: Option<i64>,
+    pub size_root_fs: Option<i64>,
+    pub labels: HashMap<String, String>,
+    pub state: String,
+    pub status: String,
+    pub mounts: Vec<ContainerMount>,
+}
+
+impl Container {
+    pub fn new(container_summary: ContainerSummary) -> Self {
+        Self {
+            id: container_summary.id.unwrap_or_default(),
+            names: container_summary
+                .names
+                .unwrap_or_default()
+                .into_iter()
+                .map(|name| name.trim_start_matches('/').to_string())
+                .collect(),
+            image: container_summary.image.unwrap_or_default(),
+            image_id: container_summary.image_id.unwrap_or_default(),
+            command: container_summary.command.unwrap_or_default(),
+            created: DateTime::from_timestamp(container_summary.created.unwrap_or_default(), 0)
+                .unwrap_or_default()
+                .fixed_offset(),
+            ports: container_summary
+                .ports
+                .unwrap_or_default()
+                .into_iter()
+                .map(ContainerPort::new)
+                .collect(),
+            size_rw: container_summary.size_rw,
+            size_root_fs: container_summary.size_root_fs,
+            labels: container_summary.labels.unwrap_or_default(),
+            state: container_summary
+                .state
+                .map(|s| s.to_string())
+                .unwrap_or_default(),
+            status: container_summary.status.unwrap_or_default(),
+            mounts: container_summary
+                .mounts
+                .unwrap_or_default()
+                .into_iter()
+                .map(ContainerMount::new)
+                .collect(),
+        }
+    }
+
+    pub fn short_version(&self, span: Span) -> Value {
+        let mut base = Record::new();
+        self.base_add_names(&mut base, span);
+        self.base_add_image(&mut base, span);
+        self.base_add_status(&mut base, span);
+        self.base_add_size(&mut base, span);
+        Value::record(base, span)
+    }
+
+    pub fn standard_version(&self, span: Span) -> Value {
+        let mut base = Record::new();
+        self.base_add_id(&mut base, span);
+        self.base_add_image(&mut base, span);
+        self.base_add_command(&mut base, span);
+        self.base_add_created(&mut base, span);
+        self.base_add_status(&mut base, span);
+        self.base_add_ports(&mut base, span);
+        self.base_add_names(&mut base, span);
+        self.base_add_size(&mut base, span);
+        Value::record(base, span)
+    }
+
+    pub fn base_add_id(&self, base: &mut Record, span: Span) {
+        let short_id = shorten_id(&self.id);
+        base.insert("id".to_string(), Value::string(&short_id, span));
+    }
+
+    pub fn base_add_names(&self, base: &mut Record, span: Span) {
+        base.insert("names".to_string(), self.names_value(span));
+    }
+
+    pub fn base_add_image(&self, base: &mut Record, span: Span) {
+        base.insert("image".to_string(), Value::string(&self.image, span));
+    }
+
+    pub fn base_add_image_id(&self, base: &mut Record, span: Span) {
+        let short_id = shorten_id(&self.image_id);
+        base.insert("image_id".to_string(), Value::string(&short_id, span));
+    }
+
+    pub fn base_add_command(&self, base: &mut Record, span: Span) {
+        base.insert("command".to_string(), Value::string(&self.command, span));
+    }
+
+    pub fn base_add_created(&self, base: &mut Record, span: Span) {
+        base.insert(
+            "created".to_string(),
+            Value::Date {
+                val: self.created,
+                internal_span: span,
+            },
+        );
+    }
+
+    pub fn base_add_state(&self, base: &mut Record, span: Span) {
+        base.insert("state".to_string(), Value::string(&self.state, span));
+    }
+
+    pub fn base_add_status(&self, base: &mut Record, span: Span) {
+        base.insert("status".to_string(), Value::string(&self.status, span));
+    }
+
+    pub fn base_add_ports(&self, base: &mut Record, span: Span) {
+        base.insert("ports".to_string(), self.ports_value(span));
+    }
+
+    pub fn base_add_labels(&self, base: &mut Record, span: Span) {
+        base.insert("labels".to_string(), self.labels_value(span));
+    }
+
+    pub fn base_add_mounts(&self, base: &mut Record, span: Span) {
+        base.insert("mounts".to_string(), self.mounts_value(span));
+    }
+
+    /// Sizes are only reported by the daemon when `--size` is requested,
+    /// so the columns are omitted otherwise.
+    pub fn base_add_size(&self, base: &mut Record, span: Span) {
+        if let Some(size_rw) = self.size_rw {
+            base.insert(
+                "size_rw".to_string(),
+                Value::filesize(Filesize::new(size_rw), span),
+            );
+        }
+        if let Some(size_root_fs) = self.size_root_fs {
+            base.insert(
+                "size_root_fs".to_string(),
+                Value::filesize(Filesize::new(size_root_fs), span),
+            );
+        }
+    }
+
+    fn names_value(&self, span: Span) -> Value {
+        Value::list(
+            self.names
+                .iter()
+                .map(|name| Value::string(name, span))
+                .collect(),
+            span,
+        )
+    }
+
+    fn ports_value(&self, span: Span) -> Value {
+        Value::list(
+            self.ports.iter().map(|port| port.to_value(span)).collect(),
+            span,
+        )
+    }
+
+    fn labels_value(&self, span: Span) -> Value {
+        let mut labels = self.labels.iter().collect::<Vec<_>>();
+        labels.sort();
+        Value::record(
+            labels
+                .into_iter()
+                .map(|(k, v)| (k.clone(), Value::string(v, span)))
+                .collect(),
+            span,
+        )
+    }
+
+    fn mounts_value(&self, span: Span) -> Value {
+        Value::list(
+            self.mounts
+                .iter()
+                .map(|mount| mount.to_value(span))
+                .collect(),
+            span,
+        )
+    }
+}
+
+#[typetag::serde]
+impl CustomValue for Container {
+    fn clone_value(&self, span: Span) -> Value {
+        Value::custom(Box::new(self.clone()), span)
+    }
+
+    fn type_name(&self) -> String {
+        "Container".into()
+    }
+
+    fn to_base_value(&self, span: Span) -> Result<Value, ShellError> {
+        let mut record = Record::new();
+        self.base_add_id(&mut record, span);
+        self.base_add_names(&mut record, span);
+        self.base_add_image(&mut record, span);
+        self.base_add_image_id(&mut record, span);
+        self.base_add_command(&mut record, span);
+        self.base_add_created(&mut record, span);
+        self.base_add_state(&mut record, span);
+        self.base_add_status(&mut record, span);
+        self.base_add_ports(&mut record, span);
+        self.base_add_labels(&mut record, span);
+        self.base_add_mounts(&mut record, span);
+        self.base_add_size(&mut record, span);
+        Ok(Value::record(record, span))
+    }
+
+    fn follow_path_string(
+        &self,
+        self_span: Span,
+        column_name: String,
+        path_span: Span,
+    ) -> Result<Value, ShellError> {
+        match column_name.as_str() {
+            "id" => Ok(Value::string(self.id.clone(), self_span)),
+            "names" => Ok(self.names_value(self_span)),
+            "image" => Ok(Value::string(self.image.clone(), self_span)),
+            "image_id" => Ok(Value::string(self.image_id.clone(), self_span)),
+            "command" => Ok(Value::string(self.command.clone(), self_span)),
+            "created" => Ok(Value::Date {
+                val: self.created,
+                internal_span: self_span,
+            }),
+            "state" => Ok(Value::string(self.state.clone(), self_span)),
+            "status" => Ok(Value::string(self.status.clone(), self_span)),
+            "ports" => Ok(self.ports_value(self_span)),
+            "labels" => Ok(self.labels_value(self_span)),
+            "mounts" => Ok(self.mounts_value(self_span)),
+            "size_rw" => Ok(self
+                .size_rw
+                .map(|size| Value::filesize(Filesize::new(size), self_span))
+                .unwrap_or(Value::nothing(self_span))),
+            "size_root_fs" => Ok(self
+                .size_root_fs
+                .map(|size| Value::filesize(Filesize::new(size), self_span))
+                .unwrap_or(Value::nothing(self_span))),
+            _ => Err(ShellError::InvalidValue {
+                valid: "one of {id, names, image, image_id, command, created, state, status, ports, labels, mounts, size_rw, size_root_fs}"
+                    .into(),
+                actual: column_name,
+                span: path_span,
+            }),
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_mut_any(&mut self) -> &mut dyn Any {
+        self
+    }
+}
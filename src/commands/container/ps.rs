@@ -0,0 +1,165 @@
+//! This module is for command `ndocker ps`.
+
+use crate::NdockerPlugin;
+use crate::commands::container::Container;
+
+use nu_plugin::PluginCommand;
+use nu_protocol::{CustomValue, Example, IntoPipelineData, Record, Span, Value};
+
+use bollard::query_parameters::ListContainersOptionsBuilder;
+use chrono::DateTime;
+
+use tokio::runtime::Runtime;
+
+pub struct PsCommand;
+
+impl PluginCommand for PsCommand {
+    type Plugin = NdockerPlugin;
+
+    fn name(&self) -> &str {
+        "ndocker ps"
+    }
+
+    fn signature(&self) -> nu_protocol::Signature {
+        nu_protocol::Signature::build("ndocker ps")
+            .input_output_types(vec![(
+                nu_protocol::Type::Nothing,
+                nu_protocol::Type::table(),
+            )])
+            .switch(
+                "all",
+                "Show all containers, including the stopped ones.",
+                Some('a'),
+            )
+            .switch("short", "Show a short version of information", Some('s'))
+            .switch(
+                "wide",
+                "Show all the information of the container.",
+                Some('w'),
+            )
+            .switch("size", "Show the size of the containers.", None)
+    }
+
+    fn description(&self) -> &str {
+        "List Docker containers and their infomation."
+    }
+
+    fn run(
+        &self,
+        plugin: &Self::Plugin,
+        _engine: &nu_plugin::EngineInterface,
+        call: &nu_plugin::EvaluatedCall,
+        _input: nu_protocol::PipelineData,
+    ) -> Result<nu_protocol::PipelineData, nu_protocol::LabeledError> {
+        let rt = Runtime::new().map_err(|e| {
+            nu_protocol::LabeledError::new(format!("Failed to create runtime: {e}"))
+        })?;
+        let options = ListContainersOptionsBuilder::new()
+            .all(call.has_flag("all")?)
+            .size(call.has_flag("size")?)
+            .build();
+        let containers = rt
+            .block_on(plugin.docker_socket.list_containers(Some(options)))
+            .map_err(|e| {
+                nu_protocol::LabeledError::new(format!("Failed to list Docker containers: {e}"))
+            })?;
+
+        if let Some(timeout) = plugin.timeout {
+            rt.shutdown_timeout(timeout);
+            return Err(nu_protocol::LabeledError::new(format!(
+                "Timeout: Operation time exceeded {} seconds",
+                timeout.as_secs()
+            )));
+        }
+
+        let span = call.head;
+        let result: Vec<Value> = if call.has_flag("wide") == Ok(true) {
+            containers
+                .into_iter()
+                .map(Container::new)
+                .map(|container| Container::clone_value(&container, span))
+                .collect::<Vec<_>>()
+        } else if call.has_flag("short") == Ok(true) {
+            containers
+                .into_iter()
+                .map(Container::new)
+                .map(|container| container.short_version(span))
+                .collect::<Vec<_>>()
+        } else {
+            containers
+                .into_iter()
+                .map(Container::new)
+                .map(|container| container.standard_version(span))
+                .collect::<Vec<_>>()
+        };
+
+        let result = Value::List {
+            vals: result,
+            internal_span: span,
+        };
+        Ok(result.into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<nu_protocol::Example<'_>> {
+        vec![
+            Example {
+                description: "List running Docker containers",
+                example: "ndocker ps",
+                result: Some(Value::test_list(vec![Value::test_record(
+                    Record::from_raw_cols_vals(
+                        vec![
+                            "id".into(),
+                            "image".into(),
+                            "command".into(),
+                            "created".into(),
+                            "status".into(),
+                            "ports".into(),
+                            "names".into(),
+                        ],
+                        vec![
+                            Value::test_string("3f4e8a9b2c1d"),
+                            Value::test_string("nginx:latest"),
+                            Value::test_string("/docker-entrypoint.sh nginx -g 'daemon off;'"),
+                            Value::test_date(
+                                DateTime::parse_from_rfc3339("2025-01-15T08:30:00+00:00").unwrap(),
+                            ),
+                            Value::test_string("Up 2 hours"),
+                            Value::test_list(vec![]),
+                            Value::test_list(vec![Value::test_string("web")]),
+                        ],
+                        Span::unknown(),
+                        Span::unknown(),
+                    )
+                    .unwrap(),
+                )])),
+            },
+            Example {
+                description: "List all Docker containers, including the stopped ones, in a short version",
+                example: "ndocker ps -a -s",
+                result: Some(Value::test_list(vec![Value::test_record(
+                    Record::from_raw_cols_vals(
+                        vec!["names".into(), "image".into(), "status".into()],
+                        vec![
+                            Value::test_list(vec![Value::test_string("web")]),
+                            Value::test_string("nginx:latest"),
+                            Value::test_string("Exited (0) 5 minutes ago"),
+                        ],
+                        Span::unknown(),
+                        Span::unknown(),
+                    )
+                    .unwrap(),
+                )])),
+            },
+            Example {
+                description: "List running containers with their sizes and sort by writable layer size",
+                example: "ndocker ps --size | sort-by size_rw",
+                result: None,
+            },
+            Example {
+                description: "Show the labels of all containers",
+                example: "ndocker ps -a -w | each { |c| $c.labels }",
+                result: None,
+            },
+        ]
+    }
+}
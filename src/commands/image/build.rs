@@ -0,0 +1,999 @@
+//! This module is for command `ndocker image build`.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::NdockerPlugin;
+use crate::commands::image::Image;
+use crate::commands::image::progress::ProgressPrinter;
+use crate::utils::dockerignore::{DOCKERIGNORE_FILE, Dockerignore};
+use crate::utils::file::{list_directory, read_build_context_stream};
+
+use bollard::body_stream;
+use bollard::grpc::build::{
+    ImageBuildFrontendOptions, ImageBuildLoadInput, ImageBuildPlatform, SecretSource,
+};
+use bollard::grpc::driver::Build;
+use bollard::grpc::driver::moby::Moby;
+use bollard::moby::buildkit::v1::StatusResponse;
+use bollard::query_parameters::{BuildImageOptions, BuildImageOptionsBuilder, BuilderVersion};
+use bollard::secret::{BuildInfoAux, ImageId};
+use bytes::{Bytes, BytesMut};
+use chrono::{DateTime, Utc};
+use nu_plugin::PluginCommand;
+use nu_protocol::{
+    CustomValue, Example, Filesize, IntoPipelineData, LabeledError, PipelineData, Record, Span,
+    Value,
+};
+
+use futures_util::stream::{Stream, StreamExt};
+
+/// The name of a Dockerfile given on the pipeline, in the build context.
+const PIPED_DOCKERFILE: &str = ".dockerfile.ndocker";
+/// The only SSH agent BuildKit can be given, the one of `SSH_AUTH_SOCK`.
+const DEFAULT_SSH: &str = "default";
+
+/// A step of a BuildKit build, merged from the updates of its progress.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Vertex {
+    digest: String,
+    name: String,
+    cached: bool,
+    started: Option<DateTime<Utc>>,
+    completed: Option<DateTime<Utc>>,
+    error: String,
+}
+
+/// What the daemon reported about a build.
+#[derive(Default)]
+struct BuildOutcome {
+    id: Option<String>,
+    vertexes: Vec<Vertex>,
+    error: Option<String>,
+}
+
+/// The vertexes of a BuildKit build, in the order they appeared.
+#[derive(Default)]
+struct BuildkitTrace {
+    vertexes: Vec<Vertex>,
+}
+
+impl BuildkitTrace {
+    /// Merge the updates of a trace message and print them like the plain
+    /// progress of the Docker CLI, one `#N` line per event of vertex N.
+    fn update(&mut self, status: StatusResponse, progress: &mut ProgressPrinter) {
+        for vertex in status.vertexes {
+            let index = match self
+                .vertexes
+                .iter()
+                .position(|known| known.digest == vertex.digest)
+            {
+                Some(index) => index,
+                None => {
+                    self.vertexes.push(Vertex {
+                        digest: vertex.digest.clone(),
+                        ..Vertex::default()
+                    });
+                    self.vertexes.len() - 1
+                }
+            };
+            let started = vertex.started.and_then(|started| {
+                DateTime::from_timestamp(started.seconds, started.nanos as u32)
+            });
+            let completed = vertex.completed.and_then(|completed| {
+                DateTime::from_timestamp(completed.seconds, completed.nanos as u32)
+            });
+            let known = &mut self.vertexes[index];
+            let number = index + 1;
+            if !vertex.name.is_empty() {
+                known.name = vertex.name;
+            }
+            if (started.is_some() || vertex.cached) && known.started.is_none() && !known.cached {
+                progress.log(&format!("#{} {}\n", number, known.name));
+            }
+            if vertex.cached && !known.cached {
+                known.cached = true;
+                progress.log(&format!("#{} CACHED\n", number));
+            }
+            known.started = started.or(known.started);
+            if !vertex.error.is_empty() && known.error.is_empty() {
+                known.error = vertex.error;
+                progress.log(&format!("#{} ERROR: {}\n", number, known.error));
+            }
+            if let Some(completed) = completed
+                && known.completed.is_none()
+            {
+                known.completed = Some(completed);
+                if let Some(started) = known.started
+                    && !known.cached
+                    && known.error.is_empty()
+                {
+                    let seconds = (completed - started).num_milliseconds() as f64 / 1000.0;
+                    progress.log(&format!("#{} DONE {:.1}s\n", number, seconds));
+                }
+            }
+        }
+
+        for log in status.logs {
+            let number = self
+                .vertexes
+                .iter()
+                .position(|vertex| vertex.digest == log.vertex)
+                .map_or(0, |index| index + 1);
+            for line in String::from_utf8_lossy(&log.msg).lines() {
+                progress.log(&format!("#{} {}\n", number, line));
+            }
+        }
+    }
+}
+
+pub struct ImageBuildCommand;
+
+impl ImageBuildCommand {
+    /// Read a `{key: value}` flag, like the build arguments or the labels.
+    fn record_flag(
+        call: &nu_plugin::EvaluatedCall,
+        name: &str,
+    ) -> Result<Option<HashMap<String, String>>, LabeledError> {
+        let Some(value) = call.get_flag_value(name) else {
+            return Ok(None);
+        };
+        let record = value.as_record().map_err(|_| {
+            LabeledError::new(format!("Invalid --{name}"))
+                .with_label("Expected a record", value.span())
+        })?;
+        record
+            .iter()
+            .map(|(key, value)| {
+                value
+                    .coerce_string()
+                    .map(|value| (key.clone(), value))
+                    .map_err(|_| {
+                        LabeledError::new(format!("Invalid --{name}")).with_label(
+                            format!("Unsupported type: {}", value.get_type()),
+                            value.span(),
+                        )
+                    })
+            })
+            .collect::<Result<HashMap<_, _>, _>>()
+            .map(Some)
+    }
+
+    /// Read the Dockerfile given on the pipeline, if any.
+    fn piped_dockerfile(input: PipelineData) -> Result<Option<String>, LabeledError> {
+        match input {
+            PipelineData::Empty => Ok(None),
+            PipelineData::Value(Value::String { val, .. }, _) => Ok(Some(val)),
+            PipelineData::Value(Value::Nothing { .. }, _) => Ok(None),
+            PipelineData::ByteStream(stream, _) => stream
+                .into_string()
+                .map(Some)
+                .map_err(|e| LabeledError::new(format!("Failed to read the Dockerfile: {e}"))),
+            PipelineData::Value(value, _) => Err(LabeledError::new("Invalid input").with_label(
+                format!("Expected a Dockerfile string, found {}", value.get_type()),
+                value.span(),
+            )),
+            PipelineData::ListStream(stream, _) => Err(LabeledError::new("Invalid input")
+                .with_label("Expected a Dockerfile string, found a list", stream.span())),
+        }
+    }
+
+    /// Build with the build endpoint of the daemon. With BuildKit, the
+    /// Docker client serves the session of the build and decodes its
+    /// progress.
+    async fn build_streamed(
+        plugin: &NdockerPlugin,
+        options: BuildImageOptions,
+        context_stream: impl Stream<Item = Bytes> + Send + 'static,
+        progress: &mut ProgressPrinter,
+    ) -> BuildOutcome {
+        let mut response_stream =
+            plugin
+                .docker_socket
+                .build_image(options, None, Some(body_stream(context_stream)));
+        let mut outcome = BuildOutcome::default();
+        let mut trace = BuildkitTrace::default();
+        while let Some(response) = response_stream.next().await {
+            let response = match response {
+                Ok(response) => response,
+                Err(e) => {
+                    outcome.error = Some(e.to_string());
+                    break;
+                }
+            };
+            if let Some(error) = response.error {
+                outcome.error = Some(error);
+                break;
+            }
+            match response.aux {
+                Some(BuildInfoAux::Default(ImageId { id: Some(built_id) })) => {
+                    outcome.id = Some(built_id);
+                }
+                Some(BuildInfoAux::BuildKit(status)) => trace.update(status, progress),
+                _ => {}
+            }
+            if let Some(output) = response.stream {
+                if outcome.id.is_none() {
+                    outcome.id = Self::parse_built_id(&output);
+                }
+                progress.log(&output);
+            }
+            progress.update(response.id, response.status, response.progress_detail);
+        }
+        outcome.vertexes = trace.vertexes;
+        outcome
+    }
+
+    /// Build with BuildKit over the gRPC API of the daemon, which serves the
+    /// secrets and the SSH agent to the build. The Docker client uploads the
+    /// context as a single message, so it is gathered in memory first. The
+    /// API reports no progress, and the built image is only known by its tag.
+    async fn build_with_grpc(
+        plugin: &NdockerPlugin,
+        tag: &str,
+        frontend_options: ImageBuildFrontendOptions,
+        context_stream: impl Stream<Item = Bytes> + Send + 'static,
+    ) -> BuildOutcome {
+        let context = context_stream
+            .fold(BytesMut::new(), |mut context, chunk| async move {
+                context.extend_from_slice(&chunk);
+                context
+            })
+            .await;
+        let result = Moby::new(&plugin.docker_socket)
+            .docker_build(
+                tag,
+                frontend_options,
+                ImageBuildLoadInput::Upload(context.freeze()),
+                None,
+            )
+            .await;
+        BuildOutcome {
+            error: result.err().map(|e| e.to_string()),
+            ..BuildOutcome::default()
+        }
+    }
+
+    /// Parse a `--secret` like the Docker CLI does, `id=<id>,src=<path>`. The
+    /// relative paths are resolved against the current directory.
+    fn parse_secret(secret: &str, current_path: &Path) -> Result<(String, SecretSource), String> {
+        let mut id = None;
+        let mut src = None;
+        for field in secret.split(',') {
+            match field.split_once('=') {
+                Some(("id", value)) => id = Some(value),
+                Some(("src" | "source", value)) => src = Some(value),
+                Some(("type", "file")) => {}
+                Some(("type", value)) => {
+                    return Err(format!("Unsupported secret type {value}, expected file"));
+                }
+                _ => {
+                    return Err(format!(
+                        "Invalid field {field}, expected id=<id>,src=<path>"
+                    ));
+                }
+            }
+        }
+        let (Some(id), Some(src)) = (id, src) else {
+            return Err(format!(
+                "Invalid secret {secret}, expected id=<id>,src=<path>"
+            ));
+        };
+        if id.is_empty() {
+            return Err("The id of a secret can't be empty".to_string());
+        }
+        let path = current_path.join(src);
+        if !path.is_file() {
+            return Err(format!("Secret file not found: {}", path.display()));
+        }
+        Ok((id.to_string(), SecretSource::File(path)))
+    }
+
+    /// A unique id for the BuildKit session of a build.
+    fn session_id() -> String {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        format!("ndocker-{}-{:x}", std::process::id(), nanos)
+    }
+
+    /// Parse a platform in the format os/arch[/variant].
+    fn parse_platform(platform: &str) -> Option<ImageBuildPlatform> {
+        let mut parts = platform.split('/');
+        let os = parts.next().filter(|os| !os.is_empty())?;
+        let architecture = parts.next().filter(|arch| !arch.is_empty())?;
+        let variant = parts.next().map(str::to_string);
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(ImageBuildPlatform {
+            os: os.to_string(),
+            architecture: architecture.to_string(),
+            variant,
+        })
+    }
+
+    fn vertex_value(vertex: &Vertex, span: Span) -> Value {
+        let date_value = |date: Option<DateTime<Utc>>| {
+            date.map_or(Value::nothing(span), |date| {
+                Value::date(date.fixed_offset(), span)
+            })
+        };
+        let duration = match (vertex.started, vertex.completed) {
+            (Some(started), Some(completed)) => (completed - started)
+                .num_nanoseconds()
+                .map_or(Value::nothing(span), |nanos| Value::duration(nanos, span)),
+            _ => Value::nothing(span),
+        };
+        let mut base = Record::new();
+        base.insert("name".to_string(), Value::string(&vertex.name, span));
+        base.insert("cached".to_string(), Value::bool(vertex.cached, span));
+        base.insert("started".to_string(), date_value(vertex.started));
+        base.insert("completed".to_string(), date_value(vertex.completed));
+        base.insert("duration".to_string(), duration);
+        base.insert(
+            "error".to_string(),
+            if vertex.error.is_empty() {
+                Value::nothing(span)
+            } else {
+                Value::string(&vertex.error, span)
+            },
+        );
+        Value::record(base, span)
+    }
+
+    /// List the files of the build context that would be sent to the daemon,
+    /// with the total size of the context.
+    fn context_preview(
+        root: Option<PathBuf>,
+        exclude: &Dockerignore,
+        dockerfile: Option<String>,
+        span: Span,
+    ) -> Result<Value, LabeledError> {
+        let entries = match &root {
+            Some(root) => list_directory(root, exclude)
+                .map_err(|e| LabeledError::new(format!("Failed to read the build context: {e}")))?,
+            None => Vec::new(),
+        };
+        let mut files = entries
+            .into_iter()
+            .map(|entry| {
+                let file_type = if entry.file_type.is_dir() {
+                    "dir"
+                } else if entry.file_type.is_symlink() {
+                    "symlink"
+                } else if entry.file_type.is_file() {
+                    "file"
+                } else {
+                    "other"
+                };
+                (
+                    entry.path.to_string_lossy().into_owned(),
+                    file_type,
+                    entry.size,
+                )
+            })
+            .collect::<Vec<_>>();
+        if let Some(dockerfile) = dockerfile {
+            files.push((
+                PIPED_DOCKERFILE.to_string(),
+                "file",
+                dockerfile.len() as u64,
+            ));
+        }
+
+        let total = files.iter().map(|(_, _, size)| size).sum::<u64>();
+        let files = files
+            .into_iter()
+            .map(|(path, file_type, size)| {
+                let mut row = Record::new();
+                row.insert("path".to_string(), Value::string(path, span));
+                row.insert("type".to_string(), Value::string(file_type, span));
+                row.insert(
+                    "size".to_string(),
+                    Value::filesize(Filesize::new(size as i64), span),
+                );
+                Value::record(row, span)
+            })
+            .collect();
+        let mut result = Record::new();
+        result.insert("files".to_string(), Value::list(files, span));
+        result.insert(
+            "total".to_string(),
+            Value::filesize(Filesize::new(total as i64), span),
+        );
+        Ok(Value::record(result, span))
+    }
+
+    /// Parse the id of the built image from the `Successfully built` line of
+    /// the daemons that don't report it otherwise.
+    fn parse_built_id(output: &str) -> Option<String> {
+        output
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("Successfully built "))
+            .map(|id| id.trim().to_string())
+    }
+}
+
+impl PluginCommand for ImageBuildCommand {
+    type Plugin = NdockerPlugin;
+
+    fn name(&self) -> &str {
+        "ndocker image build"
+    }
+
+    fn description(&self) -> &str {
+        "Build a Docker image from a Dockerfile and a context directory."
+    }
+
+    fn extra_description(&self) -> &str {
+        "With --buildkit, the image is returned along with the steps of the build. --secret and --ssh build with BuildKit over its gRPC API instead, which reports no progress, so they don't go with --buildkit. That API also takes the build context in a single message, so the context is held in memory, and it only names the built image by its tag, so --tag is required."
+    }
+
+    fn signature(&self) -> nu_protocol::Signature {
+        nu_protocol::Signature::build("ndocker image build")
+            .input_output_types(vec![
+                (
+                    nu_protocol::Type::Nothing,
+                    nu_protocol::Type::Custom("Image".to_string().into_boxed_str()),
+                ),
+                (
+                    nu_protocol::Type::String,
+                    nu_protocol::Type::Custom("Image".to_string().into_boxed_str()),
+                ),
+                (nu_protocol::Type::Nothing, nu_protocol::Type::record()),
+                (nu_protocol::Type::String, nu_protocol::Type::record()),
+            ])
+            .switch("quiet", "Suppress the build output", Some('q'))
+            .named(
+                "tag",
+                nu_protocol::Type::String.to_shape(),
+                "The name and optionally the tag of the image, in the name:tag format",
+                Some('t'),
+            )
+            .named(
+                "file",
+                nu_protocol::Type::String.to_shape(),
+                "The path of the Dockerfile in the context, defaults to \"Dockerfile\"",
+                Some('f'),
+            )
+            .named(
+                "build-arg",
+                nu_protocol::Type::record().to_shape(),
+                "The build arguments, like {VERSION: 1.2}",
+                None,
+            )
+            .named(
+                "label",
+                nu_protocol::Type::record().to_shape(),
+                "The labels to set on the image",
+                None,
+            )
+            .named(
+                "target",
+                nu_protocol::Type::String.to_shape(),
+                "The stage of a multi-stage Dockerfile to build",
+                None,
+            )
+            .named(
+                "platform",
+                nu_protocol::Type::String.to_shape(),
+                "Build for the platform, in the format os[/arch[/variant]], for example: linux/amd64",
+                None,
+            )
+            .switch("no-cache", "Don't use the cache of previous builds", None)
+            .switch("pull", "Always pull the newer versions of the base images", None)
+            .switch(
+                "dry-run",
+                "Don't build, list the files of the build context instead",
+                None,
+            )
+            .switch(
+                "buildkit",
+                "Build with BuildKit, and return the image along with the steps of the build, not with --secret or --ssh",
+                None,
+            )
+            .named(
+                "secret",
+                nu_protocol::Type::List(Box::new(nu_protocol::Type::String)).to_shape(),
+                "The secret files to expose to the build, like [id=npm,src=.npmrc], builds with BuildKit without progress and needs --tag",
+                None,
+            )
+            .named(
+                "ssh",
+                nu_protocol::Type::String.to_shape(),
+                "Forward the SSH agent of SSH_AUTH_SOCK to the build, the only value being default, builds with BuildKit without progress and needs --tag",
+                None,
+            )
+            .optional(
+                "PATH",
+                nu_protocol::Type::String.to_shape(),
+                "The directory of the build context, defaults to the current directory unless a Dockerfile is piped in.",
+            )
+    }
+
+    fn run(
+        &self,
+        plugin: &Self::Plugin,
+        engine: &nu_plugin::EngineInterface,
+        call: &nu_plugin::EvaluatedCall,
+        input: nu_protocol::PipelineData,
+    ) -> Result<nu_protocol::PipelineData, nu_protocol::LabeledError> {
+        let rt = tokio::runtime::Runtime::new()
+            .map_err(|e| LabeledError::new(format!("Failed to create runtime: {e}")))?;
+
+        let current_path = engine
+            .get_current_dir()
+            .map_err(|e| LabeledError::new(format!("Failed to get current directory: {e}")))?;
+        let dockerfile = Self::piped_dockerfile(input)?;
+        let path = call.opt::<String>(0)?;
+        if dockerfile.is_some() && call.get_flag_value("file").is_some() {
+            return Err(LabeledError::new("Invalid --file")
+                .with_label("Doesn't apply when the Dockerfile is piped in", call.head));
+        }
+
+        // A piped Dockerfile is built without context unless PATH is given.
+        let root = match (&path, &dockerfile) {
+            (None, Some(_)) => None,
+            (path, _) => {
+                let root = Path::new(&current_path).join(path.as_deref().unwrap_or("."));
+                if !root.is_dir() {
+                    let span = call
+                        .positional
+                        .first()
+                        .map_or(call.head, |value| value.span());
+                    return Err(LabeledError::new("Invalid build context")
+                        .with_label(format!("Not a directory: {}", root.display()), span));
+                }
+                Some(root)
+            }
+        };
+        let file = call.get_flag::<String>("file")?;
+        let mut exclude = match &root {
+            Some(root) => Dockerignore::read(root)
+                .map_err(|e| LabeledError::new(format!("Failed to read the build context: {e}")))?,
+            None => Dockerignore::default(),
+        };
+        // The daemon needs them whatever `.dockerignore` says.
+        exclude.keep(DOCKERIGNORE_FILE);
+        exclude.keep(file.as_deref().unwrap_or("Dockerfile"));
+
+        if call.has_flag("dry-run")? {
+            return Self::context_preview(root, &exclude, dockerfile, call.head)
+                .map(|preview| preview.into_pipeline_data());
+        }
+
+        let tag = call.get_flag::<String>("tag")?;
+        let dockerfile_path = match (&dockerfile, file) {
+            (Some(_), _) => Some(PIPED_DOCKERFILE.to_string()),
+            (None, file) => file,
+        };
+        let build_args = Self::record_flag(call, "build-arg")?;
+        let labels = Self::record_flag(call, "label")?;
+        let target = call.get_flag::<String>("target")?;
+        let platform = call.get_flag::<String>("platform")?;
+        let no_cache = call.has_flag("no-cache")?;
+        let pull = call.has_flag("pull")?;
+
+        let mut secrets = Vec::new();
+        if let Some(value) = call.get_flag_value("secret") {
+            for secret in call.get_flag::<Vec<String>>("secret")?.unwrap_or_default() {
+                secrets.push(
+                    Self::parse_secret(&secret, Path::new(&current_path)).map_err(|e| {
+                        LabeledError::new("Invalid --secret").with_label(e, value.span())
+                    })?,
+                );
+            }
+        }
+        let ssh = match call.get_flag_value("ssh") {
+            Some(value) if value.coerce_str().ok().as_deref() != Some(DEFAULT_SSH) => {
+                return Err(LabeledError::new("Invalid --ssh").with_label(
+                    "Only the agent of SSH_AUTH_SOCK can be forwarded, expected default",
+                    value.span(),
+                ));
+            }
+            Some(value) if std::env::var_os("SSH_AUTH_SOCK").is_none() => {
+                return Err(LabeledError::new("Invalid --ssh").with_label(
+                    "SSH_AUTH_SOCK isn't set in the environment of the plugin",
+                    value.span(),
+                ));
+            }
+            Some(_) => true,
+            None => false,
+        };
+
+        // The secrets and the SSH agent are only served over the gRPC API.
+        let grpc = !secrets.is_empty() || ssh;
+        let buildkit = call.has_flag("buildkit")?;
+        if grpc && tag.is_none() {
+            return Err(LabeledError::new("Missing --tag").with_label(
+                "--secret and --ssh need a tag to find the built image",
+                call.head,
+            ));
+        }
+        if grpc && buildkit {
+            return Err(LabeledError::new("Invalid --buildkit").with_label(
+                "The steps of a build with --secret or --ssh can't be reported",
+                call.head,
+            ));
+        }
+
+        let frontend_options = if grpc {
+            let mut frontend_options = ImageBuildFrontendOptions::builder()
+                .nocache(no_cache)
+                .pull(pull)
+                .enable_ssh(ssh);
+            if let Some(dockerfile_path) = &dockerfile_path {
+                frontend_options = frontend_options.dockerfile(Path::new(dockerfile_path));
+            }
+            for (key, value) in build_args.iter().flatten() {
+                frontend_options = frontend_options.buildarg(key, value);
+            }
+            for (key, value) in labels.iter().flatten() {
+                frontend_options = frontend_options.label(key, value);
+            }
+            if let Some(target) = &target {
+                frontend_options = frontend_options.target(target);
+            }
+            if let Some(platform) = &platform {
+                let platform = Self::parse_platform(platform).ok_or_else(|| {
+                    let span = call
+                        .get_flag_value("platform")
+                        .map_or(call.head, |value| value.span());
+                    LabeledError::new("Invalid --platform")
+                        .with_label("Expected os/arch[/variant]", span)
+                })?;
+                frontend_options = frontend_options.platforms(&platform);
+            }
+            for (id, source) in &secrets {
+                frontend_options = frontend_options.set_secret(id, source);
+            }
+            Some(frontend_options.build())
+        } else {
+            None
+        };
+
+        let mut options = BuildImageOptionsBuilder::new().nocache(no_cache).rm(true);
+        if let Some(tag) = &tag {
+            options = options.t(tag);
+        }
+        if let Some(dockerfile_path) = &dockerfile_path {
+            options = options.dockerfile(dockerfile_path);
+        }
+        if let Some(build_args) = &build_args {
+            options = options.buildargs(build_args);
+        }
+        if let Some(labels) = &labels {
+            options = options.labels(labels);
+        }
+        if let Some(target) = &target {
+            options = options.target(target);
+        }
+        if let Some(platform) = &platform {
+            options = options.platform(platform);
+        }
+        if pull {
+            options = options.pull("1");
+        }
+        if buildkit {
+            options = options
+                .version(BuilderVersion::BuilderBuildKit)
+                .session(&Self::session_id());
+        }
+
+        let mut progress = ProgressPrinter::new(call.has_flag("quiet")?);
+        let (image, vertexes) = rt.block_on(async {
+            let (context_stream, archiver) = read_build_context_stream(
+                root,
+                exclude,
+                dockerfile.map(|dockerfile| (PIPED_DOCKERFILE.to_string(), dockerfile)),
+            );
+            let outcome = match (frontend_options, &tag) {
+                (Some(frontend_options), Some(tag)) => {
+                    Self::build_with_grpc(plugin, tag, frontend_options, context_stream).await
+                }
+                _ => {
+                    Self::build_streamed(plugin, options.build(), context_stream, &mut progress)
+                        .await
+                }
+            };
+            progress.finish();
+
+            // A failed read truncates the context, so report it before the
+            // daemon error.
+            if outcome.error.is_none() || archiver.is_finished() {
+                archiver
+                    .join()
+                    .map_err(|_| LabeledError::new("Failed to read the build context"))?
+                    .map_err(|e| {
+                        LabeledError::new(format!("Failed to read the build context: {e}"))
+                    })?;
+            }
+            if let Some(e) = outcome.error {
+                return Err(LabeledError::new(format!("Failed to build image: {e}")));
+            }
+
+            let reference = outcome.id.or(tag).ok_or_else(|| {
+                LabeledError::new("Failed to build image: the daemon didn't report the image")
+            })?;
+            let image = Image::from_reference(&plugin.docker_socket, &reference).await?;
+            Ok((image, outcome.vertexes))
+        })?;
+
+        if let Some(timeout) = plugin.timeout {
+            rt.shutdown_timeout(timeout);
+            return Err(LabeledError::new(format!(
+                "Timeout: Operation time exceeded {} seconds",
+                timeout.as_secs()
+            )));
+        }
+
+        let span = call.head;
+        if !buildkit {
+            return Ok(image.clone_value(span).into_pipeline_data());
+        }
+        let mut result = Record::new();
+        result.insert("image".to_string(), image.clone_value(span));
+        result.insert(
+            "vertexes".to_string(),
+            Value::list(
+                vertexes
+                    .iter()
+                    .map(|vertex| Self::vertex_value(vertex, span))
+                    .collect(),
+                span,
+            ),
+        );
+        Ok(Value::record(result, span).into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<nu_protocol::Example<'_>> {
+        vec![
+            Example {
+                description: "Build and tag the image of the current directory",
+                example: "ndocker image build -t app:1.0",
+                result: None,
+            },
+            Example {
+                description: "Build a stage of a Dockerfile with build arguments and labels",
+                example: "ndocker image build ./app --target runtime --build-arg {VERSION: 1.2} --label {team: backend}",
+                result: None,
+            },
+            Example {
+                description: "Show the largest files sent with the build context",
+                example: "(ndocker image build --dry-run).files | sort-by size --reverse | first 10",
+                result: None,
+            },
+            Example {
+                description: "Build with BuildKit and show the slowest steps",
+                example: "ndocker image build --buildkit -t app:1.0 | get vertexes | sort-by duration --reverse | first 5",
+                result: None,
+            },
+            Example {
+                description: "Build with a secret file mounted by RUN --mount=type=secret,id=npm and the SSH agent",
+                example: "ndocker image build -t app:1.0 --secret [id=npm,src=.npmrc] --ssh default",
+                result: None,
+            },
+            Example {
+                description: "Build a Dockerfile from the pipeline, without context",
+                example: "\"FROM alpine:3.21\\nRUN apk add --no-cache curl\" | ndocker image build -t curl",
+                result: None,
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nu_plugin::EvaluatedCall;
+    use nu_protocol::{IntoSpanned, record};
+
+    fn call_with(name: &str, value: Value) -> EvaluatedCall {
+        EvaluatedCall::new(Span::test_data())
+            .with_named(name.into_spanned(Span::test_data()), value)
+    }
+
+    #[test]
+    fn record_flags_are_declared_as_records() {
+        let signature = ImageBuildCommand.signature();
+        for name in ["build-arg", "label"] {
+            let flag = signature.get_long_flag(name).unwrap();
+            assert_eq!(flag.arg, Some(nu_protocol::Type::record().to_shape()));
+        }
+    }
+
+    #[test]
+    fn record_flag_coerces_the_values_to_strings() {
+        let call = call_with(
+            "build-arg",
+            Value::test_record(record! {
+                "VERSION" => Value::test_float(1.2),
+                "DEBUG" => Value::test_bool(true),
+                "NAME" => Value::test_string("app"),
+            }),
+        );
+        let args = ImageBuildCommand::record_flag(&call, "build-arg")
+            .unwrap()
+            .unwrap();
+        assert_eq!(args["VERSION"], "1.2");
+        assert_eq!(args["DEBUG"], "true");
+        assert_eq!(args["NAME"], "app");
+        assert_eq!(
+            ImageBuildCommand::record_flag(&call, "label").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn record_flag_rejects_nested_values() {
+        let call = call_with(
+            "label",
+            Value::test_record(record! {
+                "tags" => Value::test_list(vec![Value::test_string("a")]),
+            }),
+        );
+        assert!(ImageBuildCommand::record_flag(&call, "label").is_err());
+    }
+
+    #[test]
+    fn piped_dockerfile_reads_strings_only() {
+        let dockerfile = "FROM alpine\n";
+        assert_eq!(
+            ImageBuildCommand::piped_dockerfile(
+                Value::test_string(dockerfile).into_pipeline_data()
+            )
+            .unwrap(),
+            Some(dockerfile.to_string())
+        );
+        assert_eq!(
+            ImageBuildCommand::piped_dockerfile(PipelineData::Empty).unwrap(),
+            None
+        );
+        assert!(
+            ImageBuildCommand::piped_dockerfile(Value::test_int(1).into_pipeline_data()).is_err()
+        );
+    }
+
+    #[test]
+    fn parse_built_id_reads_the_classic_output() {
+        let output = "Step 2/2 : RUN true\n ---> Running in 5b5c\nSuccessfully built 8daff9993116\nSuccessfully tagged app:1.0\n";
+        assert_eq!(
+            ImageBuildCommand::parse_built_id(output),
+            Some("8daff9993116".to_string())
+        );
+        assert_eq!(
+            ImageBuildCommand::parse_built_id("Step 1/2 : FROM alpine"),
+            None
+        );
+    }
+
+    fn status(vertexes: Vec<bollard::moby::buildkit::v1::Vertex>) -> StatusResponse {
+        StatusResponse {
+            vertexes,
+            ..StatusResponse::default()
+        }
+    }
+
+    fn vertex(digest: &str, name: &str) -> bollard::moby::buildkit::v1::Vertex {
+        bollard::moby::buildkit::v1::Vertex {
+            digest: digest.to_string(),
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn trace_merges_the_updates_of_a_vertex() {
+        let mut progress = ProgressPrinter::new(true);
+        let mut trace = BuildkitTrace::default();
+
+        let mut started = vertex("sha256:run", "[2/2] RUN make");
+        started.started.get_or_insert_default().seconds = 1_700_000_000;
+        trace.update(
+            status(vec![vertex("sha256:from", "[1/2] FROM alpine"), started]),
+            &mut progress,
+        );
+        let mut cached = vertex("sha256:from", "");
+        cached.cached = true;
+        let mut completed = vertex("sha256:run", "");
+        let timestamp = completed.completed.get_or_insert_default();
+        timestamp.seconds = 1_700_000_002;
+        timestamp.nanos = 500_000_000;
+        completed.error = "exit code: 2".to_string();
+        trace.update(status(vec![cached, completed]), &mut progress);
+
+        assert_eq!(trace.vertexes.len(), 2);
+        assert_eq!(trace.vertexes[0].name, "[1/2] FROM alpine");
+        assert!(trace.vertexes[0].cached);
+        let run = &trace.vertexes[1];
+        assert_eq!(run.name, "[2/2] RUN make");
+        assert_eq!(run.error, "exit code: 2");
+        assert_eq!(
+            run.completed.unwrap() - run.started.unwrap(),
+            chrono::Duration::milliseconds(2500)
+        );
+    }
+
+    #[test]
+    fn vertex_value_reports_the_duration_and_error() {
+        let started = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let vertex = Vertex {
+            digest: "sha256:run".to_string(),
+            name: "[2/2] RUN make".to_string(),
+            cached: false,
+            started: Some(started),
+            completed: Some(started + chrono::Duration::seconds(3)),
+            error: String::new(),
+        };
+        let value = ImageBuildCommand::vertex_value(&vertex, Span::test_data());
+        let record = value.as_record().unwrap();
+        assert_eq!(
+            record.get("name"),
+            Some(&Value::test_string("[2/2] RUN make"))
+        );
+        assert_eq!(
+            record.get("duration"),
+            Some(&Value::test_duration(3_000_000_000))
+        );
+        assert_eq!(record.get("error"), Some(&Value::test_nothing()));
+
+        let pending = Vertex {
+            completed: None,
+            error: "canceled".to_string(),
+            ..vertex
+        };
+        let value = ImageBuildCommand::vertex_value(&pending, Span::test_data());
+        let record = value.as_record().unwrap();
+        assert_eq!(record.get("duration"), Some(&Value::test_nothing()));
+        assert_eq!(record.get("error"), Some(&Value::test_string("canceled")));
+    }
+
+    #[test]
+    fn parse_secret_reads_the_docker_cli_format() {
+        let dir = std::env::temp_dir().join(format!("ndocker-secret-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(".npmrc"), "token").unwrap();
+
+        let (id, source) = ImageBuildCommand::parse_secret("id=npm,src=.npmrc", &dir).unwrap();
+        assert_eq!(id, "npm");
+        assert!(matches!(source, SecretSource::File(path) if path == dir.join(".npmrc")));
+        assert!(ImageBuildCommand::parse_secret("type=file,source=.npmrc,id=npm", &dir).is_ok());
+
+        for invalid in [
+            "id=npm",
+            "src=.npmrc",
+            "id=,src=.npmrc",
+            "id=npm,src=missing",
+            "id=npm,env=TOKEN",
+            "type=env,id=npm,src=.npmrc",
+        ] {
+            assert!(
+                ImageBuildCommand::parse_secret(invalid, &dir).is_err(),
+                "{invalid}"
+            );
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parse_platform_needs_the_os_and_architecture() {
+        let platform = ImageBuildCommand::parse_platform("linux/arm64/v8").unwrap();
+        assert_eq!(platform.os, "linux");
+        assert_eq!(platform.architecture, "arm64");
+        assert_eq!(platform.variant.as_deref(), Some("v8"));
+        assert_eq!(
+            ImageBuildCommand::parse_platform("linux/amd64")
+                .unwrap()
+                .variant,
+            None
+        );
+        for invalid in ["linux", "linux/", "/amd64", "linux/arm/v7/extra"] {
+            assert!(
+                ImageBuildCommand::parse_platform(invalid).is_none(),
+                "{invalid}"
+            );
+        }
+    }
+}
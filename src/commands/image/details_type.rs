@@ -0,0 +1,264 @@
+//! This module is for custom value `ImageDetails`.
+
+use std::any::Any;
+
+use chrono::{DateTime, FixedOffset, Utc};
+
+use bollard::secret::{ImageConfig, ImageInspect};
+
+use nu_protocol::{CustomValue, Filesize, Record, ShellError, Span, Value};
+use serde::{Deserialize, Serialize};
+
+/// The configuration containers created from an image start with.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImageDetailsConfig {
+    pub user: String,
+    pub env: Vec<(String, String)>,
+    pub exposed_ports: Vec<String>,
+    pub entrypoint: Vec<String>,
+    pub cmd: Vec<String>,
+    pub working_dir: String,
+    pub labels: Vec<(String, String)>,
+    pub volumes: Vec<String>,
+    pub on_build: Vec<String>,
+    pub stop_signal: String,
+}
+
+impl ImageDetailsConfig {
+    pub fn new(config: ImageConfig) -> Self {
+        let mut exposed_ports = config
+            .exposed_ports
+            .unwrap_or_default()
+            .into_keys()
+            .collect::<Vec<_>>();
+        exposed_ports.sort();
+        let mut labels = config
+            .labels
+            .unwrap_or_default()
+            .into_iter()
+            .collect::<Vec<_>>();
+        labels.sort();
+        let mut volumes = config
+            .volumes
+            .unwrap_or_default()
+            .into_keys()
+            .collect::<Vec<_>>();
+        volumes.sort();
+
+        Self {
+            user: config.user.unwrap_or_default(),
+            env: config
+                .env
+                .unwrap_or_default()
+                .into_iter()
+                .map(|variable| match variable.split_once('=') {
+                    Some((key, value)) => (key.to_string(), value.to_string()),
+                    None => (variable, String::new()),
+                })
+                .collect(),
+            exposed_ports,
+            entrypoint: config.entrypoint.unwrap_or_default(),
+            cmd: config.cmd.unwrap_or_default(),
+            working_dir: config.working_dir.unwrap_or_default(),
+            labels,
+            volumes,
+            on_build: config.on_build.unwrap_or_default(),
+            stop_signal: config.stop_signal.unwrap_or_default(),
+        }
+    }
+
+    pub fn to_value(&self, span: Span) -> Value {
+        let mut base = Record::new();
+        base.insert("user".to_string(), Value::string(&self.user, span));
+        base.insert("env".to_string(), pairs_value(&self.env, span));
+        base.insert(
+            "exposed_ports".to_string(),
+            strings_value(&self.exposed_ports, span),
+        );
+        base.insert(
+            "entrypoint".to_string(),
+            strings_value(&self.entrypoint, span),
+        );
+        base.insert("cmd".to_string(), strings_value(&self.cmd, span));
+        base.insert(
+            "working_dir".to_string(),
+            Value::string(&self.working_dir, span),
+        );
+        base.insert("labels".to_string(), pairs_value(&self.labels, span));
+        base.insert("volumes".to_string(), strings_value(&self.volumes, span));
+        base.insert("on_build".to_string(), strings_value(&self.on_build, span));
+        base.insert(
+            "stop_signal".to_string(),
+            Value::string(&self.stop_signal, span),
+        );
+        Value::record(base, span)
+    }
+}
+
+/// This struct contains the detailed information about an image.
+/// It is also a custom value that can be used in NuShell.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageDetails {
+    pub id: String,
+    pub repo_tags: Vec<String>,
+    pub repo_digests: Vec<String>,
+    pub parent: String,
+    pub comment: String,
+    pub created: Option<DateTime<FixedOffset>>,
+    pub docker_version: String,
+    pub author: String,
+    pub config: ImageDetailsConfig,
+    pub architecture: String,
+    pub variant: String,
+    pub os: String,
+    pub size: i64,
+    pub rootfs_type: String,
+    pub layers: Vec<String>,
+    pub last_tag_time: Option<DateTime<FixedOffset>>,
+}
+
+impl ImageDetails {
+    pub fn new(image_inspect: ImageInspect) -> Self {
+        let parse_date = |date: Option<DateTime<Utc>>| date.map(|date| date.fixed_offset());
+        let root_fs = image_inspect.root_fs;
+        Self {
+            id: image_inspect.id.unwrap_or_default(),
+            repo_tags: image_inspect.repo_tags.unwrap_or_default(),
+            repo_digests: image_inspect.repo_digests.unwrap_or_default(),
+            parent: image_inspect.parent.unwrap_or_default(),
+            comment: image_inspect.comment.unwrap_or_default(),
+            created: parse_date(image_inspect.created),
+            docker_version: image_inspect.docker_version.unwrap_or_default(),
+            author: image_inspect.author.unwrap_or_default(),
+            config: image_inspect
+                .config
+                .map(ImageDetailsConfig::new)
+                .unwrap_or_default(),
+            architecture: image_inspect.architecture.unwrap_or_default(),
+            variant: image_inspect.variant.unwrap_or_default(),
+            os: image_inspect.os.unwrap_or_default(),
+            size: image_inspect.size.unwrap_or_default(),
+            rootfs_type: root_fs
+                .as_ref()
+                .map(|root_fs| root_fs.typ.clone())
+                .unwrap_or_default(),
+            layers: root_fs
+                .and_then(|root_fs| root_fs.layers)
+                .unwrap_or_default(),
+            last_tag_time: parse_date(
+                image_inspect
+                    .metadata
+                    .and_then(|metadata| metadata.last_tag_time),
+            ),
+        }
+    }
+
+    fn rootfs_value(&self, span: Span) -> Value {
+        let mut base = Record::new();
+        base.insert("type".to_string(), Value::string(&self.rootfs_type, span));
+        base.insert("layers".to_string(), strings_value(&self.layers, span));
+        Value::record(base, span)
+    }
+
+    fn column_value(&self, column_name: &str, span: Span) -> Option<Value> {
+        let date_value = |date: Option<DateTime<FixedOffset>>| match date {
+            Some(date) => Value::date(date, span),
+            None => Value::nothing(span),
+        };
+        Some(match column_name {
+            "id" => Value::string(&self.id, span),
+            "repotags" => strings_value(&self.repo_tags, span),
+            "repodigests" => strings_value(&self.repo_digests, span),
+            "parent" => Value::string(&self.parent, span),
+            "comment" => Value::string(&self.comment, span),
+            "created" => date_value(self.created),
+            "docker_version" => Value::string(&self.docker_version, span),
+            "author" => Value::string(&self.author, span),
+            "config" => self.config.to_value(span),
+            "architecture" => Value::string(&self.architecture, span),
+            "variant" => Value::string(&self.variant, span),
+            "os" => Value::string(&self.os, span),
+            "size" => Value::filesize(Filesize::new(self.size), span),
+            "rootfs" => self.rootfs_value(span),
+            "last_tag_time" => date_value(self.last_tag_time),
+            _ => return None,
+        })
+    }
+}
+
+/// The columns of `ImageDetails`, in display order.
+const COLUMNS: &[&str] = &[
+    "id",
+    "repotags",
+    "repodigests",
+    "parent",
+    "comment",
+    "created",
+    "docker_version",
+    "author",
+    "config",
+    "architecture",
+    "variant",
+    "os",
+    "size",
+    "rootfs",
+    "last_tag_time",
+];
+
+pub fn strings_value(strings: &[String], span: Span) -> Value {
+    Value::list(
+        strings.iter().map(|s| Value::string(s, span)).collect(),
+        span,
+    )
+}
+
+fn pairs_value(pairs: &[(String, String)], span: Span) -> Value {
+    let mut record = Record::new();
+    for (key, value) in pairs {
+        record.insert(key.clone(), Value::string(value, span));
+    }
+    Value::record(record, span)
+}
+
+#[typetag::serde]
+impl CustomValue for ImageDetails {
+    fn clone_value(&self, span: Span) -> Value {
+        Value::custom(Box::new(self.clone()), span)
+    }
+
+    fn type_name(&self) -> String {
+        "ImageDetails".into()
+    }
+
+    fn to_base_value(&self, span: Span) -> Result<Value, ShellError> {
+        let mut base = Record::new();
+        for column in COLUMNS {
+            if let Some(value) = self.column_value(column, span) {
+                base.insert(column.to_string(), value);
+            }
+        }
+        Ok(Value::record(base, span))
+    }
+
+    fn follow_path_string(
+        &self,
+        self_span: Span,
+        column_name: String,
+        path_span: Span,
+    ) -> Result<Value, ShellError> {
+        self.column_value(&column_name, self_span)
+            .ok_or_else(|| ShellError::InvalidValue {
+                valid: format!("one of {{{}}}", COLUMNS.join(", ")),
+                actual: column_name,
+                span: path_span,
+            })
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_mut_any(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,478 @@
+//! This module is for command `ndocker image diff`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::NdockerPlugin;
+use crate::commands::image::details_type::strings_value;
+use crate::commands::image::{ImageDetails, ImageHistory};
+
+use nu_plugin::PluginCommand;
+use nu_protocol::{Example, Filesize, IntoPipelineData, LabeledError, Record, Span, Value};
+
+pub struct ImageDiffCommand;
+
+impl ImageDiffCommand {
+    fn string_or_nothing(value: Option<&str>, span: Span) -> Value {
+        value.map_or(Value::nothing(span), |value| Value::string(value, span))
+    }
+
+    /// Compare two sets of `key=value` pairs, like environment variables or labels.
+    fn diff_pairs(old: &[(String, String)], new: &[(String, String)], span: Span) -> Value {
+        let old = old.iter().cloned().collect::<BTreeMap<_, _>>();
+        let new = new.iter().cloned().collect::<BTreeMap<_, _>>();
+        let keys = old.keys().chain(new.keys()).collect::<BTreeSet<_>>();
+        let rows = keys
+            .into_iter()
+            .filter_map(|key| {
+                let (old_value, new_value) = (old.get(key), new.get(key));
+                let status = match (old_value, new_value) {
+                    (None, Some(_)) => "added",
+                    (Some(_), None) => "removed",
+                    (Some(old_value), Some(new_value)) if old_value != new_value => "changed",
+                    _ => return None,
+                };
+                let mut row = Record::new();
+                row.insert("key".to_string(), Value::string(key, span));
+                row.insert("status".to_string(), Value::string(status, span));
+                row.insert(
+                    "old".to_string(),
+                    Self::string_or_nothing(old_value.map(String::as_str), span),
+                );
+                row.insert(
+                    "new".to_string(),
+                    Self::string_or_nothing(new_value.map(String::as_str), span),
+                );
+                Some(Value::record(row, span))
+            })
+            .collect();
+        Value::list(rows, span)
+    }
+
+    /// Compare two sets of values, like exposed ports or volumes.
+    fn diff_sets(old: &[String], new: &[String], column: &str, span: Span) -> Value {
+        let old = old.iter().collect::<BTreeSet<_>>();
+        let new = new.iter().collect::<BTreeSet<_>>();
+        let removed = old.difference(&new).map(|value| (*value, "removed"));
+        let added = new.difference(&old).map(|value| (*value, "added"));
+        let rows = removed
+            .chain(added)
+            .map(|(value, status)| {
+                let mut row = Record::new();
+                row.insert(column.to_string(), Value::string(value, span));
+                row.insert("status".to_string(), Value::string(status, span));
+                Value::record(row, span)
+            })
+            .collect();
+        Value::list(rows, span)
+    }
+
+    /// Compare the single valued settings, like the entrypoint or the user.
+    /// The entrypoint and the command are compared as lists, as
+    /// `["sh", "-c", "a b"]` and `["sh", "-c", "a", "b"]` differ.
+    fn diff_config(old: &ImageDetails, new: &ImageDetails, span: Span) -> Value {
+        let string = |value: &str| Value::string(value, span);
+        let fields = [
+            (
+                "entrypoint",
+                strings_value(&old.config.entrypoint, span),
+                strings_value(&new.config.entrypoint, span),
+            ),
+            (
+                "cmd",
+                strings_value(&old.config.cmd, span),
+                strings_value(&new.config.cmd, span),
+            ),
+            ("user", string(&old.config.user), string(&new.config.user)),
+            (
+                "working_dir",
+                string(&old.config.working_dir),
+                string(&new.config.working_dir),
+            ),
+            (
+                "stop_signal",
+                string(&old.config.stop_signal),
+                string(&new.config.stop_signal),
+            ),
+            ("os", string(&old.os), string(&new.os)),
+            (
+                "architecture",
+                string(&old.architecture),
+                string(&new.architecture),
+            ),
+        ];
+        let rows = fields
+            .into_iter()
+            .filter(|(_, old_value, new_value)| old_value != new_value)
+            .map(|(field, old_value, new_value)| {
+                let mut row = Record::new();
+                row.insert("field".to_string(), Value::string(field, span));
+                row.insert("old".to_string(), old_value);
+                row.insert("new".to_string(), new_value);
+                Value::record(row, span)
+            })
+            .collect();
+        Value::list(rows, span)
+    }
+
+    /// Align the histories, oldest first, on the longest common sequence of
+    /// `created_by`. The steps found in both images are `same`, or `rebuilt`
+    /// when their size changed.
+    fn diff_history(old: &[ImageHistory], new: &[ImageHistory], span: Span) -> Value {
+        let (n, m) = (old.len(), new.len());
+        let mut lengths = vec![vec![0usize; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lengths[i][j] = if old[i].created_by == new[j].created_by {
+                    lengths[i + 1][j + 1] + 1
+                } else {
+                    lengths[i + 1][j].max(lengths[i][j + 1])
+                };
+            }
+        }
+
+        let row = |status: &str, old: Option<&ImageHistory>, new: Option<&ImageHistory>| {
+            let created_by = old.or(new).map(|step| step.created_by.as_str());
+            let size = |step: Option<&ImageHistory>| {
+                step.map_or(Value::nothing(span), |step| {
+                    Value::filesize(Filesize::new(step.size), span)
+                })
+            };
+            let mut row = Record::new();
+            row.insert("status".to_string(), Value::string(status, span));
+            row.insert(
+                "created_by".to_string(),
+                Self::string_or_nothing(created_by, span),
+            );
+            row.insert("old_size".to_string(), size(old));
+            row.insert("new_size".to_string(), size(new));
+            Value::record(row, span)
+        };
+
+        let mut rows = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && old[i].created_by == new[j].created_by {
+                let status = if old[i].size == new[j].size {
+                    "same"
+                } else {
+                    "rebuilt"
+                };
+                rows.push(row(status, Some(&old[i]), Some(&new[j])));
+                i += 1;
+                j += 1;
+            } else if i < n && (j == m || lengths[i + 1][j] >= lengths[i][j + 1]) {
+                rows.push(row("removed", Some(&old[i]), None));
+                i += 1;
+            } else {
+                rows.push(row("added", None, Some(&new[j])));
+                j += 1;
+            }
+        }
+        Value::list(rows, span)
+    }
+}
+
+impl PluginCommand for ImageDiffCommand {
+    type Plugin = NdockerPlugin;
+
+    fn name(&self) -> &str {
+        "ndocker image diff"
+    }
+
+    fn description(&self) -> &str {
+        "Compare the configuration and the history of two Docker images."
+    }
+
+    fn signature(&self) -> nu_protocol::Signature {
+        nu_protocol::Signature::build("ndocker image diff")
+            .input_output_types(vec![(
+                nu_protocol::Type::Nothing,
+                nu_protocol::Type::record(),
+            )])
+            .required(
+                "OLD",
+                nu_protocol::Type::String.to_shape(),
+                "The ID or name of the image to compare from.",
+            )
+            .required(
+                "NEW",
+                nu_protocol::Type::String.to_shape(),
+                "The ID or name of the image to compare to.",
+            )
+    }
+
+    fn run(
+        &self,
+        plugin: &Self::Plugin,
+        _engine: &nu_plugin::EngineInterface,
+        call: &nu_plugin::EvaluatedCall,
+        _input: nu_protocol::PipelineData,
+    ) -> Result<nu_protocol::PipelineData, nu_protocol::LabeledError> {
+        let rt = tokio::runtime::Runtime::new()
+            .map_err(|e| LabeledError::new(format!("Failed to create runtime: {e}")))?;
+        let old_image: String = call.req(0)?;
+        let new_image: String = call.req(1)?;
+
+        let fetch = |image: String, span: Span| async move {
+            let inspect = plugin
+                .docker_socket
+                .inspect_image(&image)
+                .await
+                .map_err(|e| {
+                    LabeledError::new("Failed to inspect Docker image")
+                        .with_label(e.to_string(), span)
+                })?;
+            let mut history = plugin
+                .docker_socket
+                .image_history(&image)
+                .await
+                .map_err(|e| {
+                    LabeledError::new("Failed to get image history").with_label(e.to_string(), span)
+                })?
+                .into_iter()
+                .map(ImageHistory::new)
+                .collect::<Vec<_>>();
+            history.reverse();
+            Ok::<_, LabeledError>((ImageDetails::new(inspect), history))
+        };
+        let ((old, old_history), (new, new_history)) = rt.block_on(async {
+            futures_util::try_join!(
+                fetch(old_image, call.positional[0].span()),
+                fetch(new_image, call.positional[1].span())
+            )
+        })?;
+
+        if let Some(timeout) = plugin.timeout {
+            rt.shutdown_timeout(timeout);
+            return Err(LabeledError::new(format!(
+                "Timeout: Operation time exceeded {} seconds",
+                timeout.as_secs()
+            )));
+        }
+
+        let span = call.head;
+        let mut result = Record::new();
+        result.insert(
+            "env".to_string(),
+            Self::diff_pairs(&old.config.env, &new.config.env, span),
+        );
+        result.insert(
+            "labels".to_string(),
+            Self::diff_pairs(&old.config.labels, &new.config.labels, span),
+        );
+        result.insert(
+            "exposed_ports".to_string(),
+            Self::diff_sets(
+                &old.config.exposed_ports,
+                &new.config.exposed_ports,
+                "port",
+                span,
+            ),
+        );
+        result.insert(
+            "volumes".to_string(),
+            Self::diff_sets(&old.config.volumes, &new.config.volumes, "path", span),
+        );
+        result.insert("config".to_string(), Self::diff_config(&old, &new, span));
+        result.insert(
+            "history".to_string(),
+            Self::diff_history(&old_history, &new_history, span),
+        );
+        Ok(Value::record(result, span).into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<nu_protocol::Example<'_>> {
+        vec![
+            Example {
+                description: "Show what changed in the environment with a base image bump",
+                example: "(ndocker image diff rust:1.83.0 rust:1.84.0).env",
+                result: None,
+            },
+            Example {
+                description: "Show the build steps that differ between two images",
+                example: "ndocker image diff app:1.0 app:1.1 | get history | where status != same",
+                result: None,
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bollard::secret::{ImageConfig, ImageInspect};
+
+    fn details(entrypoint: &[&str], cmd: &[&str], user: &str) -> ImageDetails {
+        let strings = |values: &[&str]| Some(values.iter().map(|v| v.to_string()).collect());
+        ImageDetails::new(ImageInspect {
+            config: Some(ImageConfig {
+                entrypoint: strings(entrypoint),
+                cmd: strings(cmd),
+                user: Some(user.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+    }
+
+    fn field_names(value: &Value) -> Vec<String> {
+        value
+            .as_list()
+            .unwrap()
+            .iter()
+            .map(|row| {
+                row.as_record()
+                    .unwrap()
+                    .get("field")
+                    .unwrap()
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn diff_config_compares_the_commands_as_lists() {
+        let span = Span::test_data();
+        let old = details(&["sh", "-c"], &["echo a b"], "root");
+        let new = details(&["sh", "-c"], &["echo", "a", "b"], "root");
+        let diff = ImageDiffCommand::diff_config(&old, &new, span);
+        assert_eq!(field_names(&diff), vec!["cmd"]);
+        let row = diff.as_list().unwrap()[0].as_record().unwrap();
+        assert_eq!(
+            row.get("old"),
+            Some(&Value::test_list(vec![Value::test_string("echo a b")]))
+        );
+        assert_eq!(
+            row.get("new"),
+            Some(&Value::test_list(vec![
+                Value::test_string("echo"),
+                Value::test_string("a"),
+                Value::test_string("b"),
+            ]))
+        );
+    }
+
+    #[test]
+    fn diff_config_lists_the_changed_fields() {
+        let span = Span::test_data();
+        let old = details(&[], &["sh"], "root");
+        let new = details(&["/entrypoint.sh"], &["sh"], "app");
+        let diff = ImageDiffCommand::diff_config(&old, &new, span);
+        assert_eq!(field_names(&diff), vec!["entrypoint", "user"]);
+        assert!(
+            ImageDiffCommand::diff_config(&old, &old, span)
+                .as_list()
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn diff_pairs_and_sets() {
+        let span = Span::test_data();
+        let pairs = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<Vec<_>>()
+        };
+        let diff = ImageDiffCommand::diff_pairs(
+            &pairs(&[("A", "1"), ("B", "2"), ("C", "3")]),
+            &pairs(&[("A", "1"), ("B", "20"), ("D", "4")]),
+            span,
+        );
+        let statuses = diff
+            .as_list()
+            .unwrap()
+            .iter()
+            .map(|row| {
+                let row = row.as_record().unwrap();
+                format!(
+                    "{} {}",
+                    row.get("key").unwrap().as_str().unwrap(),
+                    row.get("status").unwrap().as_str().unwrap()
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(statuses, vec!["B changed", "C removed", "D added"]);
+
+        let diff = ImageDiffCommand::diff_sets(
+            &["80/tcp".to_string()],
+            &["443/tcp".to_string()],
+            "port",
+            span,
+        );
+        assert_eq!(diff.as_list().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn diff_history_aligns_the_steps_on_their_command() {
+        let step = |created_by: &str, size: i64| ImageHistory {
+            id: String::new(),
+            created: Default::default(),
+            created_by: created_by.to_string(),
+            tags: Vec::new(),
+            size,
+            comment: String::new(),
+        };
+        let old = vec![
+            step("/bin/sh -c #(nop) ADD file:b7c1 in / ", 5),
+            step("RUN /bin/sh -c apt-get update", 100),
+            step("COPY app.py /app/", 10),
+            step("CMD [\"python\"]", 0),
+        ];
+        let new = vec![
+            step("/bin/sh -c #(nop) ADD file:b7c1 in / ", 5),
+            step("RUN /bin/sh -c apt-get update", 120),
+            step("RUN /bin/sh -c pip install flask", 30),
+            step("CMD [\"python\"]", 0),
+        ];
+        let diff = ImageDiffCommand::diff_history(&old, &new, Span::test_data());
+        let rows = diff
+            .as_list()
+            .unwrap()
+            .iter()
+            .map(|row| {
+                let row = row.as_record().unwrap();
+                let size = |column: &str| match row.get(column).unwrap() {
+                    Value::Filesize { val, .. } => Some(val.get()),
+                    _ => None,
+                };
+                (
+                    row.get("status").unwrap().as_str().unwrap().to_string(),
+                    row.get("created_by").unwrap().as_str().unwrap().to_string(),
+                    size("old_size"),
+                    size("new_size"),
+                )
+            })
+            .collect::<Vec<_>>();
+        let expected = [
+            (
+                "same",
+                "/bin/sh -c #(nop) ADD file:b7c1 in / ",
+                Some(5),
+                Some(5),
+            ),
+            (
+                "rebuilt",
+                "RUN /bin/sh -c apt-get update",
+                Some(100),
+                Some(120),
+            ),
+            ("removed", "COPY app.py /app/", Some(10), None),
+            ("added", "RUN /bin/sh -c pip install flask", None, Some(30)),
+            ("same", "CMD [\"python\"]", Some(0), Some(0)),
+        ]
+        .map(|(status, created_by, old_size, new_size)| {
+            (
+                status.to_string(),
+                created_by.to_string(),
+                old_size,
+                new_size,
+            )
+        });
+        assert_eq!(rows, expected);
+    }
+}
@@ -0,0 +1,295 @@
+//! This module is for command `ndocker image files`.
+
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+use crate::NdockerPlugin;
+use crate::utils::file::{LayerEntry, read_image_layers};
+
+use nu_plugin::PluginCommand;
+use nu_protocol::{Example, Filesize, IntoPipelineData, LabeledError, Record, Span, Value};
+
+/// The prefix of the files marking a deletion in a layer.
+const WHITEOUT_PREFIX: &str = ".wh.";
+/// The file marking a directory whose content from the lower layers is hidden.
+const OPAQUE_WHITEOUT: &str = ".wh..wh..opq";
+
+/// The last change of a path in the layers applied so far.
+struct FileChange {
+    entry: LayerEntry,
+    layer: usize,
+    status: &'static str,
+}
+
+pub struct ImageFilesCommand;
+
+impl ImageFilesCommand {
+    /// Mark as whited-out the paths starting with `prefix` and matching
+    /// `hidden` that were added by a layer below `layer`. Only the paths
+    /// starting with `prefix` are visited, they are contiguous in the map.
+    fn white_out(
+        files: &mut BTreeMap<String, FileChange>,
+        layer: usize,
+        prefix: &str,
+        hidden: impl Fn(&str) -> bool,
+    ) {
+        for (path, change) in files
+            .range_mut::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+            .take_while(|(path, _)| path.starts_with(prefix))
+        {
+            if change.layer < layer && change.status != "whited-out" && hidden(path) {
+                change.layer = layer;
+                change.status = "whited-out";
+            }
+        }
+    }
+
+    /// Apply the layers, oldest first, and keep the last change of each path.
+    fn apply_layers(layers: Vec<Vec<LayerEntry>>) -> BTreeMap<String, FileChange> {
+        let mut files = BTreeMap::new();
+        for (layer, entries) in layers.into_iter().enumerate() {
+            for entry in entries {
+                let (dir, name) = entry.path.rsplit_once('/').unwrap_or(("", &entry.path));
+                if name == OPAQUE_WHITEOUT {
+                    let prefix = format!("{}/", dir);
+                    Self::white_out(&mut files, layer, &prefix, |_| true);
+                } else if let Some(name) = name.strip_prefix(WHITEOUT_PREFIX) {
+                    let target = format!("{}/{}", dir, name);
+                    let prefix = format!("{}/", target);
+                    Self::white_out(&mut files, layer, &target, |path| {
+                        path == target || path.starts_with(&prefix)
+                    });
+                } else {
+                    let status = match files.get(&entry.path) {
+                        Some(FileChange { status, .. }) if *status != "whited-out" => "modified",
+                        _ => "added",
+                    };
+                    files.insert(
+                        entry.path.clone(),
+                        FileChange {
+                            entry,
+                            layer,
+                            status,
+                        },
+                    );
+                }
+            }
+        }
+        files
+    }
+
+    fn type_name(entry_type: tar::EntryType) -> &'static str {
+        match entry_type {
+            tar::EntryType::Regular | tar::EntryType::Continuous => "file",
+            tar::EntryType::Directory => "dir",
+            tar::EntryType::Symlink => "symlink",
+            tar::EntryType::Link => "hardlink",
+            tar::EntryType::Char => "char device",
+            tar::EntryType::Block => "block device",
+            tar::EntryType::Fifo => "pipe",
+            _ => "unknown",
+        }
+    }
+
+    fn file_value(change: FileChange, span: Span) -> Value {
+        let mut base = Record::new();
+        base.insert("path".to_string(), Value::string(change.entry.path, span));
+        base.insert(
+            "type".to_string(),
+            Value::string(Self::type_name(change.entry.entry_type), span),
+        );
+        base.insert(
+            "size".to_string(),
+            Value::filesize(Filesize::new(change.entry.size as i64), span),
+        );
+        base.insert(
+            "mode".to_string(),
+            Value::string(format!("{:04o}", change.entry.mode & 0o7777), span),
+        );
+        base.insert("owner".to_string(), Value::string(change.entry.owner, span));
+        base.insert("layer".to_string(), Value::int(change.layer as i64, span));
+        base.insert("status".to_string(), Value::string(change.status, span));
+        Value::record(base, span)
+    }
+}
+
+impl PluginCommand for ImageFilesCommand {
+    type Plugin = NdockerPlugin;
+
+    fn name(&self) -> &str {
+        "ndocker image files"
+    }
+
+    fn description(&self) -> &str {
+        "List the files of a Docker image with the layer that last changed them."
+    }
+
+    fn signature(&self) -> nu_protocol::Signature {
+        nu_protocol::Signature::build("ndocker image files")
+            .input_output_types(vec![(
+                nu_protocol::Type::Nothing,
+                nu_protocol::Type::table(),
+            )])
+            .named(
+                "layer",
+                nu_protocol::Type::Int.to_shape(),
+                "Only list the changes of the layer at this index, the oldest being 0",
+                Some('l'),
+            )
+            .required(
+                "IMAGE",
+                nu_protocol::Type::String.to_shape(),
+                "The ID or name of the image to list the files of.",
+            )
+    }
+
+    fn run(
+        &self,
+        plugin: &Self::Plugin,
+        _engine: &nu_plugin::EngineInterface,
+        call: &nu_plugin::EvaluatedCall,
+        _input: nu_protocol::PipelineData,
+    ) -> Result<nu_protocol::PipelineData, nu_protocol::LabeledError> {
+        let rt = tokio::runtime::Runtime::new()
+            .map_err(|e| LabeledError::new(format!("Failed to create runtime: {e}")))?;
+        let image: String = call.req(0)?;
+        let layer = call.get_flag::<i64>("layer")?;
+        if layer.is_some_and(|layer| layer < 0) {
+            return Err(LabeledError::new("Invalid --layer")
+                .with_label("Expected a positive index", call.head));
+        }
+
+        let mut layers = rt
+            .block_on(read_image_layers(plugin.docker_socket.export_image(&image)))
+            .map_err(|e| {
+                LabeledError::new("Failed to export Docker image")
+                    .with_label(e.to_string(), call.positional[0].span())
+            })?;
+
+        if let Some(timeout) = plugin.timeout {
+            rt.shutdown_timeout(timeout);
+            return Err(LabeledError::new(format!(
+                "Timeout: Operation time exceeded {} seconds",
+                timeout.as_secs()
+            )));
+        }
+
+        let span = call.head;
+        let layer = match layer {
+            Some(layer) if layer as usize >= layers.len() => {
+                return Err(LabeledError::new("Invalid --layer")
+                    .with_label(format!("The image has {} layers", layers.len()), span));
+            }
+            Some(layer) => {
+                // The layers above don't matter for the changes of this one.
+                layers.truncate(layer as usize + 1);
+                Some(layer as usize)
+            }
+            None => None,
+        };
+        let result = Self::apply_layers(layers)
+            .into_values()
+            .filter(|change| layer.is_none_or(|layer| change.layer == layer))
+            .map(|change| Self::file_value(change, span))
+            .collect();
+        Ok(Value::list(result, span).into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<nu_protocol::Example<'_>> {
+        vec![
+            Example {
+                description: "List the largest files of an image",
+                example: "ndocker image files alpine:3.21 | where type == file | sort-by size --reverse | first 10",
+                result: None,
+            },
+            Example {
+                description: "Show what the last layer of an image changed",
+                example: "ndocker image files app:1.0 --layer ((ndocker image layers app:1.0 | length) - 1)",
+                result: None,
+            },
+            Example {
+                description: "List the files deleted by the layers of an image",
+                example: "ndocker image files app:1.0 | where status == whited-out",
+                result: None,
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str) -> LayerEntry {
+        LayerEntry {
+            path: path.to_string(),
+            entry_type: tar::EntryType::Regular,
+            size: 0,
+            mode: 0o644,
+            owner: "0:0".to_string(),
+        }
+    }
+
+    fn statuses(layers: Vec<Vec<&str>>) -> Vec<(String, usize, &'static str)> {
+        let layers = layers
+            .into_iter()
+            .map(|paths| paths.into_iter().map(entry).collect())
+            .collect();
+        ImageFilesCommand::apply_layers(layers)
+            .into_iter()
+            .map(|(path, change)| (path, change.layer, change.status))
+            .collect()
+    }
+
+    fn status(path: &str, layer: usize, status: &'static str) -> (String, usize, &'static str) {
+        (path.to_string(), layer, status)
+    }
+
+    #[test]
+    fn apply_layers_tracks_the_last_change() {
+        assert_eq!(
+            statuses(vec![
+                vec!["/etc/hosts", "/app"],
+                vec!["/etc/hosts", "/bin/sh"]
+            ]),
+            vec![
+                status("/app", 0, "added"),
+                status("/bin/sh", 1, "added"),
+                status("/etc/hosts", 1, "modified"),
+            ]
+        );
+    }
+
+    #[test]
+    fn whiteout_hides_the_path_and_its_content_only() {
+        assert_eq!(
+            statuses(vec![
+                vec!["/app", "/app/main.rs", "/app2", "/app2/lib.rs"],
+                vec!["/.wh.app"],
+                vec!["/app"],
+            ]),
+            vec![
+                status("/app", 2, "added"),
+                status("/app/main.rs", 1, "whited-out"),
+                status("/app2", 0, "added"),
+                status("/app2/lib.rs", 0, "added"),
+            ]
+        );
+    }
+
+    #[test]
+    fn opaque_whiteout_hides_the_content_of_the_directory() {
+        assert_eq!(
+            statuses(vec![
+                vec!["/var/cache", "/var/cache/apt", "/var/cache2"],
+                vec!["/var/cache/.wh..wh..opq", "/var/cache/new"],
+            ]),
+            vec![
+                status("/var/cache", 0, "added"),
+                status("/var/cache/apt", 1, "whited-out"),
+                status("/var/cache/new", 1, "added"),
+                status("/var/cache2", 0, "added"),
+            ]
+        );
+    }
+}
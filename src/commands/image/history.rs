@@ -2,9 +2,10 @@
 
 use crate::NdockerPlugin;
 use crate::commands::image::ImageHistory;
+use crate::utils::dockerfile::dockerfile_from_history;
 
 use nu_plugin::PluginCommand;
-use nu_protocol::{CustomValue, IntoPipelineData, Value};
+use nu_protocol::{CustomValue, Example, IntoPipelineData, Value};
 
 pub struct ImageHistoryCommand;
 
@@ -21,15 +22,20 @@ impl PluginCommand for ImageHistoryCommand {
 
     fn signature(&self) -> nu_protocol::Signature {
         nu_protocol::Signature::build("ndocker image history")
-            .input_output_types(vec![(
-                nu_protocol::Type::Nothing,
-                nu_protocol::Type::table(),
-            )])
+            .input_output_types(vec![
+                (nu_protocol::Type::Nothing, nu_protocol::Type::table()),
+                (nu_protocol::Type::Nothing, nu_protocol::Type::String),
+            ])
             .switch(
                 "wide",
                 "Show full information of the string instead of a short version.",
                 Some('w'),
             )
+            .switch(
+                "dockerfile",
+                "Reconstruct an approximate Dockerfile from the history.",
+                Some('d'),
+            )
             .required(
                 "IMAGE",
                 nu_protocol::Type::String.to_shape(),
@@ -62,25 +68,49 @@ impl PluginCommand for ImageHistoryCommand {
             )));
         }
 
-        let span = call.head.clone();
-        let result: Vec<Value>;
-        if call.has_flag("wide") == Ok(true) {
-            result = histories
+        let span = call.head;
+        if call.has_flag("dockerfile") == Ok(true) {
+            // The daemon lists the history newest first.
+            let dockerfile = dockerfile_from_history(
+                histories
+                    .iter()
+                    .rev()
+                    .map(|history| history.created_by.as_str()),
+            );
+            return Ok(Value::string(dockerfile, span).into_pipeline_data());
+        }
+        let result: Vec<Value> = if call.has_flag("wide") == Ok(true) {
+            histories
                 .into_iter()
-                .map(|history| ImageHistory::new(history))
-                .map(|history| history.full_version(span.clone()))
-                .collect::<Vec<_>>();
+                .map(ImageHistory::new)
+                .map(|history| history.full_version(span))
+                .collect::<Vec<_>>()
         } else {
-            result = histories
+            histories
                 .into_iter()
-                .map(|history| ImageHistory::new(history))
-                .map(|history| history.clone_value(span.clone()))
-                .collect::<Vec<_>>();
-        }
+                .map(ImageHistory::new)
+                .map(|history| history.clone_value(span))
+                .collect::<Vec<_>>()
+        };
         let result = Value::List {
             vals: result,
-            internal_span: span.clone(),
+            internal_span: span,
         };
         Ok(result.into_pipeline_data())
     }
+
+    fn examples(&self) -> Vec<nu_protocol::Example<'_>> {
+        vec![
+            Example {
+                description: "Show the history of an image",
+                example: "ndocker image history rust:1.84.0",
+                result: None,
+            },
+            Example {
+                description: "Save an approximate Dockerfile of an image",
+                example: "ndocker image history app:1.0 --dockerfile | save Dockerfile",
+                result: None,
+            },
+        ]
+    }
 }
@@ -39,34 +39,28 @@ impl ImageHistory {
 
     pub fn full_version(&self, span: Span) -> Value {
         let mut base = Record::new();
-        base.insert(
-            "id".to_string(),
-            Value::string(shorten_id(&self.id), span.clone()),
-        );
+        base.insert("id".to_string(), Value::string(shorten_id(&self.id), span));
         base.insert(
             "created".to_string(),
             Value::Date {
                 val: self.created,
-                internal_span: span.clone(),
+                internal_span: span,
             },
         );
         base.insert(
             "created_by".to_string(),
-            Value::string(shorten_string(&self.created_by, 45), span.clone()),
+            Value::string(shorten_string(&self.created_by, 45), span),
         );
         base.insert(
             "tags".to_string(),
             Value::list(
-                self.tags
-                    .iter()
-                    .map(|t| Value::string(t, span.clone()))
-                    .collect(),
-                span.clone(),
+                self.tags.iter().map(|t| Value::string(t, span)).collect(),
+                span,
             ),
         );
         base.insert(
             "size".to_string(),
-            Value::filesize(Filesize::new(self.size), span.clone()),
+            Value::filesize(Filesize::new(self.size), span),
         );
         base.insert("comment".to_string(), Value::string(&self.comment, span));
         Value::record(base, span)
@@ -85,34 +79,28 @@ impl CustomValue for ImageHistory {
 
     fn to_base_value(&self, span: Span) -> Result<Value, ShellError> {
         let mut base = Record::new();
-        base.insert(
-            "id".to_string(),
-            Value::string(shorten_id(&self.id), span.clone()),
-        );
+        base.insert("id".to_string(), Value::string(shorten_id(&self.id), span));
         base.insert(
             "created".to_string(),
             Value::Date {
                 val: self.created,
-                internal_span: span.clone(),
+                internal_span: span,
             },
         );
         base.insert(
             "created_by".to_string(),
-            Value::string(shorten_string(&self.created_by, 45), span.clone()),
+            Value::string(shorten_string(&self.created_by, 45), span),
         );
         base.insert(
             "tags".to_string(),
             Value::list(
-                self.tags
-                    .iter()
-                    .map(|t| Value::string(t, span.clone()))
-                    .collect(),
-                span.clone(),
+                self.tags.iter().map(|t| Value::string(t, span)).collect(),
+                span,
             ),
         );
         base.insert(
             "size".to_string(),
-            Value::filesize(Filesize::new(self.size), span.clone()),
+            Value::filesize(Filesize::new(self.size), span),
         );
         base.insert("comment".to_string(), Value::string(&self.comment, span));
         Ok(Value::record(base, span))
@@ -2,6 +2,7 @@
 
 use crate::NdockerPlugin;
 use crate::commands::image::Image;
+use crate::commands::parse_filters;
 
 use nu_plugin::PluginCommand;
 use nu_protocol::{CustomValue, Example, IntoPipelineData, Record, Span, Value};
@@ -10,6 +11,9 @@ use bollard::query_parameters::ListImagesOptionsBuilder;
 
 use tokio::runtime::Runtime;
 
+/// Filters supported by the daemon when listing images.
+pub const IMAGE_FILTERS: &[&str] = &["dangling", "label", "reference", "before", "since", "until"];
+
 pub struct ImagesCommand;
 
 impl PluginCommand for ImagesCommand {
@@ -27,6 +31,13 @@ impl PluginCommand for ImagesCommand {
             )])
             .switch("all", "Show all the information of the image.", Some('a'))
             .switch("short", "Show a short version of information", Some('s'))
+            .switch("digests", "Show the digests of the image.", None)
+            .named(
+                "filter",
+                nu_protocol::Type::record().to_shape(),
+                "Filter images on the daemon side, with keys in {dangling, label, reference, before, since, until}",
+                Some('f'),
+            )
     }
 
     fn description(&self) -> &str {
@@ -43,12 +54,13 @@ impl PluginCommand for ImagesCommand {
         let rt = Runtime::new().map_err(|e| {
             nu_protocol::LabeledError::new(format!("Failed to create runtime: {e}"))
         })?;
+        let digests = call.has_flag("digests")?;
+        let mut options = ListImagesOptionsBuilder::new().digests(digests);
+        if let Some(filter) = call.get_flag_value("filter") {
+            options = options.filters(&parse_filters(&filter, IMAGE_FILTERS)?);
+        }
         let images = rt
-            .block_on(
-                plugin
-                    .docker_socket
-                    .list_images(Some(ListImagesOptionsBuilder::new().build())),
-            )
+            .block_on(plugin.docker_socket.list_images(Some(options.build())))
             .map_err(|e| {
                 nu_protocol::LabeledError::new(format!("Failed to list Docker images: {e}"))
             })?;
@@ -61,36 +73,36 @@ impl PluginCommand for ImagesCommand {
             )));
         }
 
-        let span = call.head.clone();
+        let span = call.head;
         let result: Vec<Value>;
         if call.has_flag("all") == Ok(true) {
             result = images
                 .into_iter()
-                .map(|image| Image::new(image))
-                .map(|image| Image::clone_value(&image, span.clone()))
+                .map(Image::new)
+                .map(|image| Image::clone_value(&image, span))
                 .collect::<Vec<_>>();
         } else if call.has_flag("short") == Ok(true) {
             result = images
                 .into_iter()
-                .map(|image| Image::new(image))
-                .map(|image| image.short_version(span.clone()))
+                .map(Image::new)
+                .map(|image| image.short_version(span, digests))
                 .collect::<Vec<_>>();
         } else {
             result = images
                 .into_iter()
-                .map(|image| Image::new(image))
-                .map(|image| image.standard_version(span.clone()))
+                .map(Image::new)
+                .map(|image| image.standard_version(span, digests))
                 .collect::<Vec<_>>();
         }
 
         let result = Value::List {
             vals: result,
-            internal_span: span.clone(),
+            internal_span: span,
         };
         Ok(result.into_pipeline_data())
     }
 
-    fn examples(&self) -> Vec<nu_protocol::Example> {
+    fn examples(&self) -> Vec<nu_protocol::Example<'_>> {
         vec![
             Example {
                 description: "List all Docker images",
@@ -161,6 +173,16 @@ impl PluginCommand for ImagesCommand {
                     .unwrap(),
                 )])),
             },
+            Example {
+                description: "List dangling images and the images built from rust",
+                example: "ndocker images --filter {dangling: true, reference: \"rust:*\"}",
+                result: None,
+            },
+            Example {
+                description: "List images with a label, created after another image, with their digests",
+                example: "ndocker images --digests --filter {label: [maintainer=me], since: \"rust:1.84.0\"}",
+                result: None,
+            },
             Example {
                 description: "List \"id\", \"repotags\" and  \"size\" of all docker images",
                 example: "ndocker images | select id repotags size",
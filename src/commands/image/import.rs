@@ -1,66 +1,149 @@
 //! This module is for command `ndocker image import`
 
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::thread::JoinHandle;
 
 use crate::NdockerPlugin;
 use crate::commands::image::Image;
-use crate::utils::file::{check_file_exists, read_file_stream};
+use crate::commands::image::progress::ProgressPrinter;
+use crate::commands::interactive::Prompt;
+use crate::utils::dockerfile::parse_instruction;
+use crate::utils::file::{
+    FileError, check_file_exists, detect_compression, read_byte_stream, read_directory_stream,
+    read_file_stream, verify_sha256,
+};
 use crate::utils::net::check_url;
 
-use bollard::{body_stream, body_full};
+use bollard::{body_stream, body_try_stream};
 use bytes::Bytes;
+use nu_glob::Pattern;
 use nu_plugin::PluginCommand;
-use nu_protocol::{CustomValue, IntoPipelineData, LabeledError, Value, PipelineData};
+use nu_protocol::{
+    CustomValue, Example, IntoPipelineData, LabeledError, PipelineData, Record, Span, Value,
+};
 
-use bollard::query_parameters::{CreateImageOptionsBuilder, ListImagesOptionsBuilder};
+use bollard::query_parameters::CreateImageOptionsBuilder;
 use bollard::secret::CreateImageInfo;
 
 use futures_util::stream::{Stream, StreamExt};
+use tokio::sync::oneshot::Receiver;
+
+type UploadStream = Pin<Box<dyn Stream<Item = Result<Bytes, std::io::Error>> + Send>>;
 
 enum ImportSrc {
     File(String),
+    Directory(PathBuf),
     Url(String),
     Stdin,
 }
 
+/// A single import, with its options checked against its source.
+struct ImportJob {
+    src: ImportSrc,
+    repo: Option<String>,
+    options: CreateImageOptionsBuilder,
+    exclude: Vec<Pattern>,
+    sha256: Option<String>,
+}
+
+/// The columns of a batch import table, with the flag they stand for.
+const BATCH_COLUMNS: &[(&str, &str)] = &[
+    ("message", "message"),
+    ("platform", "platform"),
+    ("changes", "change"),
+    ("sha256", "sha256"),
+];
+
 pub struct ImageImportCommand;
 
 impl ImageImportCommand {
+    /// Check that a tarball is an archive the daemon can import, compressed
+    /// archives are passed through as the daemon decompresses them itself.
+    /// When `sha256` is given, the returned receiver gets the mismatch of the
+    /// digest, in which case the upload is aborted.
+    async fn tarball_upload(
+        stream: impl Stream<Item = Bytes> + Send + Unpin + 'static,
+        sha256: Option<String>,
+        source: &str,
+    ) -> Result<(UploadStream, Option<Receiver<FileError>>), LabeledError> {
+        let (compression, stream) = detect_compression(stream).await;
+        if compression.is_none() {
+            return Err(nu_protocol::LabeledError::new(format!(
+                "Failed to import image from {source}: expected a tar archive, optionally compressed with gzip, bzip2, xz or zstd"
+            )));
+        }
+        Ok(match sha256 {
+            Some(sha256) => {
+                let (stream, mismatch) = verify_sha256(stream, sha256);
+                (Box::pin(stream), Some(mismatch))
+            }
+            None => (Box::pin(stream.map(Ok)), None),
+        })
+    }
+
     async fn import_from_file(
         plugin: &<ImageImportCommand as PluginCommand>::Plugin,
-        current_path: &String,
+        current_path: &str,
         path: String,
+        sha256: Option<String>,
         create_image_options: CreateImageOptionsBuilder,
-    ) -> Result<impl Stream<Item = Result<CreateImageInfo, bollard::errors::Error>>, LabeledError>
-    {
-        let file_stream = read_file_stream(current_path.clone(), path)
-            .await
-            .map_err(|e| {
+    ) -> Result<
+        (
+            impl Stream<Item = Result<CreateImageInfo, bollard::errors::Error>>,
+            JoinHandle<Result<(), FileError>>,
+            Option<Receiver<FileError>>,
+        ),
+        LabeledError,
+    > {
+        let (file_stream, reader) =
+            read_file_stream(current_path.to_string(), path).map_err(|e| {
                 nu_protocol::LabeledError::new(format!("Failed to read file stream: {}", e))
             })?;
+        let (upload, mismatch) =
+            Self::tarball_upload(Box::pin(file_stream), sha256, "file").await?;
 
-        Ok(plugin.docker_socket.create_image(
-            Some(create_image_options.from_src("-").build()),
-            Some(body_stream(file_stream)),
-            None,
+        Ok((
+            plugin.docker_socket.create_image(
+                Some(create_image_options.from_src("-").build()),
+                Some(body_try_stream(upload)),
+                None,
+            ),
+            reader,
+            mismatch,
         ))
     }
 
+    /// Forward the binary input chunk by chunk, the returned handle reports
+    /// the errors met while reading the input.
     async fn import_from_stdin(
         plugin: &<ImageImportCommand as PluginCommand>::Plugin,
         input: PipelineData,
+        sha256: Option<String>,
         create_image_options: CreateImageOptionsBuilder,
-    ) -> Result<impl Stream<Item = Result<CreateImageInfo, bollard::errors::Error>>, LabeledError>
-    {
+    ) -> Result<
+        (
+            impl Stream<Item = Result<CreateImageInfo, bollard::errors::Error>>,
+            JoinHandle<Result<(), FileError>>,
+            Option<Receiver<FileError>>,
+        ),
+        LabeledError,
+    > {
         if let PipelineData::ByteStream(stream, _) = input {
-            let bytes = stream.into_bytes().map_err(|e| {
+            let (byte_stream, reader) = read_byte_stream(stream).map_err(|e| {
                 nu_protocol::LabeledError::new(format!("Failed to read stdin: {e}"))
             })?;
-            let bytes = Bytes::from_owner(bytes);
-            Ok(plugin.docker_socket.create_image(
-                Some(create_image_options.from_src("-").build()),
-                Some(body_full(bytes)),
-                None,
+            let (upload, mismatch) =
+                Self::tarball_upload(Box::pin(byte_stream), sha256, "stdin").await?;
+            Ok((
+                plugin.docker_socket.create_image(
+                    Some(create_image_options.from_src("-").build()),
+                    Some(body_try_stream(upload)),
+                    None,
+                ),
+                reader,
+                mismatch,
             ))
         } else {
             Err(nu_protocol::LabeledError::new(
@@ -69,6 +152,102 @@ impl ImageImportCommand {
         }
     }
 
+    /// Stream a tar archive of the directory, the returned handle reports
+    /// the errors met while archiving it.
+    fn import_from_directory(
+        plugin: &<ImageImportCommand as PluginCommand>::Plugin,
+        path: PathBuf,
+        exclude: Vec<Pattern>,
+        create_image_options: CreateImageOptionsBuilder,
+    ) -> (
+        impl Stream<Item = Result<CreateImageInfo, bollard::errors::Error>>,
+        JoinHandle<Result<(), FileError>>,
+    ) {
+        let (archive_stream, archiver) = read_directory_stream(path, exclude);
+        (
+            plugin.docker_socket.create_image(
+                Some(create_image_options.from_src("-").build()),
+                Some(body_stream(archive_stream)),
+                None,
+            ),
+            archiver,
+        )
+    }
+
+    /// Parse the id of the imported image, the last status sent by the daemon.
+    /// Depending on its version it is prefixed with `sha256:` or not, the other
+    /// statuses are progress messages.
+    fn parse_image_id(status: &str) -> Option<String> {
+        let status = status.trim();
+        let digest = status.strip_prefix("sha256:").unwrap_or(status);
+        (digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit()))
+            .then(|| format!("sha256:{}", digest.to_lowercase()))
+    }
+
+    /// Wait for the import of a stream produced by `reader`, if any, and return
+    /// the id of the imported image if the daemon reported it.
+    async fn handle_streamed_import(
+        mut response_stream: impl Stream<Item = Result<CreateImageInfo, bollard::errors::Error>> + Unpin,
+        reader: Option<JoinHandle<Result<(), FileError>>>,
+        mismatch: Option<Receiver<FileError>>,
+        source: &str,
+        quiet: bool,
+    ) -> Result<Option<String>, LabeledError> {
+        let mut id = None;
+        let mut response_error = None;
+        let mut progress = ProgressPrinter::new(quiet);
+        while let Some(response) = response_stream.next().await {
+            match response {
+                Ok(CreateImageInfo {
+                    error: Some(error), ..
+                }) => {
+                    response_error = Some(error);
+                    break;
+                }
+                Ok(response) => {
+                    let status = response.status.unwrap_or_default();
+                    if let Some(image_id) = Self::parse_image_id(&status) {
+                        id = Some(image_id);
+                    }
+                    progress.update_line(Some(status), response.progress);
+                }
+                Err(e) => {
+                    response_error = Some(e.to_string());
+                    break;
+                }
+            }
+        }
+        progress.finish();
+        // A failed read truncates the upload, so report it before the
+        // daemon error. The reader is only waited for once the daemon
+        // consumed the whole input, or if it already stopped.
+        if let Some(reader) = reader
+            && (response_error.is_none() || reader.is_finished())
+        {
+            reader
+                .join()
+                .map_err(|_| nu_protocol::LabeledError::new(format!("Failed to read {source}")))?
+                .map_err(|e| {
+                    nu_protocol::LabeledError::new(format!("Failed to read {source}: {e}"))
+                })?;
+        }
+        // The upload is aborted on a checksum mismatch, which the daemon only
+        // reports as a broken request.
+        if let Some(mut mismatch) = mismatch
+            && let Ok(e) = mismatch.try_recv()
+        {
+            return Err(nu_protocol::LabeledError::new(format!(
+                "Failed to verify {source}: {e}"
+            )));
+        }
+        if let Some(e) = response_error {
+            return Err(nu_protocol::LabeledError::new(format!(
+                "Failed to import image from {source}: {e}"
+            )));
+        }
+        Ok(id)
+    }
+
     async fn import_from_url(
         plugin: &<ImageImportCommand as PluginCommand>::Plugin,
         url: String,
@@ -87,51 +266,19 @@ impl ImageImportCommand {
             Ok(ImportSrc::Stdin)
         } else if check_url(file).is_ok() {
             Ok(ImportSrc::Url(file.clone()))
+        } else if Path::new(current_path).join(file).is_dir() {
+            Ok(ImportSrc::Directory(Path::new(current_path).join(file)))
         } else if check_file_exists(current_path, file).is_ok() {
             Ok(ImportSrc::File(file.clone()))
         } else {
             Err(nu_protocol::LabeledError::new(format!(
-                "File or URL does not exist: {}",
+                "File, directory or URL does not exist: {}",
                 file
             )))
         }
     }
 
-    fn handle_url_progress(
-        response: CreateImageInfo,
-        id: &mut String,
-        line_size: &mut usize,
-    ) {
-        *id = response.status.unwrap_or_default();
-        let progress = response.progress.unwrap_or_default();
-        let output;
-
-        if id.is_empty() && progress.is_empty() {
-            return;
-        } else if id.is_empty() {
-            output = format!("\rImporting image: {}", progress);
-        } else if progress.is_empty() {
-            if *line_size == 0 {
-                eprintln!("{}", id);
-            } else {
-                eprintln!();
-                eprintln!("{}", id);
-            }
-            return;
-        } else {
-            output = format!("\r{}: {}", id, progress);
-        }
-
-        if *line_size > 0 {
-            eprint!("\r{}", " ".repeat(*line_size));
-        }
-        *line_size = output.len();
-        eprint!("\r{}", output);
-    }
-
-    fn handle_named_params(
-        call: &nu_plugin::EvaluatedCall,   
-    ) -> HashMap<String, Value> {
+    fn handle_named_params(call: &nu_plugin::EvaluatedCall) -> HashMap<String, Value> {
         call.named
             .clone()
             .into_iter()
@@ -141,9 +288,9 @@ impl ImageImportCommand {
     }
 
     fn option_get_commit_message(
-        params: &HashMap<String, Value>, 
-        create_image_options: CreateImageOptionsBuilder
-    ) -> CreateImageOptionsBuilder{
+        params: &HashMap<String, Value>,
+        create_image_options: CreateImageOptionsBuilder,
+    ) -> CreateImageOptionsBuilder {
         let mut option = create_image_options;
         if let Some(message) = params.get("message") {
             let message = message.as_str().unwrap_or_default();
@@ -153,8 +300,8 @@ impl ImageImportCommand {
     }
 
     fn option_get_platform(
-        params: &HashMap<String, Value>, 
-        create_image_options: CreateImageOptionsBuilder
+        params: &HashMap<String, Value>,
+        create_image_options: CreateImageOptionsBuilder,
     ) -> CreateImageOptionsBuilder {
         let mut option = create_image_options;
         if let Some(platform) = params.get("platform") {
@@ -164,25 +311,420 @@ impl ImageImportCommand {
         option
     }
 
+    /// Parse the glob patterns of `--exclude`, pointing at the invalid one on error.
+    fn option_get_exclude(params: &HashMap<String, Value>) -> Result<Vec<Pattern>, LabeledError> {
+        let Some(exclude) = params.get("exclude") else {
+            return Ok(Vec::new());
+        };
+        exclude
+            .as_list()
+            .map_err(|_| {
+                LabeledError::new("Invalid --exclude")
+                    .with_label("Expected a list of strings", exclude.span())
+            })?
+            .iter()
+            .map(|pattern| {
+                let glob = pattern.as_str().map_err(|_| {
+                    LabeledError::new("Invalid --exclude pattern").with_label(
+                        format!("Expected a string, found {}", pattern.get_type()),
+                        pattern.span(),
+                    )
+                })?;
+                Pattern::new(glob.trim_end_matches('/')).map_err(|e| {
+                    LabeledError::new("Invalid --exclude pattern")
+                        .with_label(e.to_string(), pattern.span())
+                })
+            })
+            .collect()
+    }
+
+    /// Parse the digest of `--sha256`, with or without the `sha256:` prefix.
+    fn option_get_sha256(params: &HashMap<String, Value>) -> Result<Option<String>, LabeledError> {
+        let Some(sha256) = params.get("sha256") else {
+            return Ok(None);
+        };
+        let digest = sha256.as_str().unwrap_or_default();
+        let digest = digest
+            .strip_prefix("sha256:")
+            .unwrap_or(digest)
+            .to_lowercase();
+        if digest.len() != 64 || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(LabeledError::new("Invalid --sha256").with_label(
+                "Expected 64 hexadecimal digits, optionally prefixed with sha256:",
+                sha256.span(),
+            ));
+        }
+        Ok(Some(digest))
+    }
+
+    /// Validate the Dockerfile instructions in `--change` before they are sent
+    /// to the daemon, pointing at the offending element on error.
     fn option_get_changes(
-        params: &HashMap<String, Value>, 
-        create_image_options: CreateImageOptionsBuilder
-    ) -> CreateImageOptionsBuilder {
+        params: &HashMap<String, Value>,
+        create_image_options: CreateImageOptionsBuilder,
+    ) -> Result<CreateImageOptionsBuilder, LabeledError> {
         let mut options = create_image_options;
         if let Some(changes) = params.get("change") {
             let changes = changes
-                .clone()
-                .into_list()
-                .unwrap_or_default()
-                .into_iter()
-                .map(|v| v.into_string().unwrap_or_default())
-                .collect::<Vec<String>>();
+                .as_list()
+                .map_err(|_| {
+                    LabeledError::new("Invalid --change")
+                        .with_label("Expected a list of strings", changes.span())
+                })?
+                .iter()
+                .map(|change| {
+                    let instruction = change.as_str().map_err(|_| {
+                        LabeledError::new("Invalid Dockerfile instruction").with_label(
+                            format!("Expected a string, found {}", change.get_type()),
+                            change.span(),
+                        )
+                    })?;
+                    parse_instruction(instruction).map_err(|e| {
+                        LabeledError::new("Invalid Dockerfile instruction")
+                            .with_label(e.to_string(), change.span())
+                    })?;
+                    Ok(instruction.to_string())
+                })
+                .collect::<Result<Vec<String>, LabeledError>>()?;
             options = options.changes(changes);
         }
-        options
+        Ok(options)
+    }
+
+    /// Build the options of an import from the flags, or the columns of a
+    /// batch row, and check they apply to its source.
+    fn import_job(
+        params: &HashMap<String, Value>,
+        src: ImportSrc,
+        repo: Option<&str>,
+        span: Span,
+    ) -> Result<ImportJob, LabeledError> {
+        let mut options = CreateImageOptionsBuilder::new();
+        if let Some(repo) = repo {
+            options = options.repo(repo);
+        }
+        options = Self::option_get_commit_message(params, options);
+        options = Self::option_get_platform(params, options);
+        options = Self::option_get_changes(params, options)?;
+        let exclude = Self::option_get_exclude(params)?;
+        if !exclude.is_empty() && !matches!(src, ImportSrc::Directory(_)) {
+            return Err(LabeledError::new("Invalid --exclude")
+                .with_label("Only applies when importing a directory", span));
+        }
+        let sha256 = Self::option_get_sha256(params)?;
+        if sha256.is_some() && !matches!(src, ImportSrc::File(_) | ImportSrc::Stdin) {
+            return Err(LabeledError::new("Invalid --sha256")
+                .with_label("Only applies when importing a tarball", span));
+        }
+        Ok(ImportJob {
+            src,
+            repo: repo.map(str::to_string),
+            options,
+            exclude,
+            sha256,
+        })
+    }
+
+    /// Build the import of a row of a batch, the columns override the flags.
+    fn batch_job(
+        row: &Value,
+        params: &HashMap<String, Value>,
+        current_path: &String,
+    ) -> Result<ImportJob, LabeledError> {
+        let record = row.as_record().map_err(|_| {
+            LabeledError::new("Invalid import").with_label(
+                format!("Expected a record, found {}", row.get_type()),
+                row.span(),
+            )
+        })?;
+        let column = |name: &str| record.get(name).filter(|value| !value.is_nothing());
+        let source = column("source")
+            .ok_or_else(|| {
+                LabeledError::new("Invalid import").with_label("Missing column source", row.span())
+            })?
+            .as_str()?;
+        if source == "-" {
+            return Err(LabeledError::new("Invalid import")
+                .with_label("The input can't be imported in a batch", row.span()));
+        }
+        let repo = column("repo").map(Value::as_str).transpose()?;
+
+        let mut params = params.clone();
+        for (name, flag) in BATCH_COLUMNS {
+            if let Some(value) = column(name) {
+                params.insert(flag.to_string(), value.clone());
+            }
+        }
+        let src = Self::get_import_source(&source.to_string(), current_path)?;
+        Self::import_job(&params, src, repo, row.span())
+    }
+
+    /// Find the imported image from the id reported by the daemon, falling
+    /// back to the repository it was tagged with.
+    async fn imported_image(
+        plugin: &<ImageImportCommand as PluginCommand>::Plugin,
+        id: Option<String>,
+        repo: Option<String>,
+    ) -> Result<Image, LabeledError> {
+        let by_id = match &id {
+            Some(id) => Some(Image::from_reference(&plugin.docker_socket, id).await),
+            None => None,
+        };
+        match (by_id, repo) {
+            (Some(Ok(image)), _) => Ok(image),
+            (_, Some(repo)) => Image::from_reference(&plugin.docker_socket, &repo)
+                .await
+                .map_err(|e| {
+                    LabeledError::new(format!(
+                        "Failed to find the imported image {repo}: {}",
+                        e.msg
+                    ))
+                }),
+            (Some(Err(e)), None) => Err(LabeledError::new(format!(
+                "Failed to find the imported image {}: {}",
+                id.unwrap_or_default(),
+                e.msg
+            ))),
+            (None, None) => Err(LabeledError::new(
+                "Failed to find the imported image: the daemon didn't report its id, tag it with REPOSITORY[:TAG] to look it up",
+            )),
+        }
+    }
+
+    /// Run an import and return the imported image. The progress is not shown
+    /// when `quiet`.
+    async fn import(
+        plugin: &<ImageImportCommand as PluginCommand>::Plugin,
+        current_path: &str,
+        job: ImportJob,
+        input: PipelineData,
+        quiet: bool,
+    ) -> Result<Image, LabeledError> {
+        let ImportJob {
+            src,
+            repo,
+            options,
+            exclude,
+            sha256,
+        } = job;
+        let id = match src {
+            ImportSrc::File(path) => {
+                let (response_stream, reader, mismatch) =
+                    Self::import_from_file(plugin, current_path, path, sha256, options).await?;
+                Self::handle_streamed_import(
+                    Box::pin(response_stream),
+                    Some(reader),
+                    mismatch,
+                    "file",
+                    quiet,
+                )
+                .await?
+            }
+            ImportSrc::Stdin => {
+                let (response_stream, reader, mismatch) =
+                    Self::import_from_stdin(plugin, input, sha256, options).await?;
+                Self::handle_streamed_import(
+                    Box::pin(response_stream),
+                    Some(reader),
+                    mismatch,
+                    "stdin",
+                    quiet,
+                )
+                .await?
+            }
+            ImportSrc::Directory(path) => {
+                let (response_stream, archiver) =
+                    Self::import_from_directory(plugin, path, exclude, options);
+                Self::handle_streamed_import(
+                    Box::pin(response_stream),
+                    Some(archiver),
+                    None,
+                    "directory",
+                    quiet,
+                )
+                .await?
+            }
+            ImportSrc::Url(url) => {
+                let mut response_stream = Self::import_from_url(plugin, url, options).await?;
+                let mut id = None;
+                let mut progress = ProgressPrinter::new(quiet);
+                while let Some(response) = response_stream.next().await {
+                    let error = match response {
+                        Ok(CreateImageInfo {
+                            error: Some(error), ..
+                        }) => Some(error),
+                        Ok(response) => {
+                            if let Some(image_id) =
+                                Self::parse_image_id(response.status.as_deref().unwrap_or_default())
+                            {
+                                id = Some(image_id);
+                            }
+                            // The download progress comes without a status.
+                            let status = response
+                                .status
+                                .filter(|status| !status.is_empty())
+                                .or_else(|| {
+                                    response
+                                        .progress
+                                        .as_ref()
+                                        .map(|_| "Importing image".to_string())
+                                });
+                            progress.update_line(status, response.progress);
+                            None
+                        }
+                        Err(e) => Some(e.to_string()),
+                    };
+                    if let Some(error) = error {
+                        progress.finish();
+                        return Err(nu_protocol::LabeledError::new(format!(
+                            "Failed to import image from URL: {error}"
+                        )));
+                    }
+                }
+                progress.finish();
+                id
+            }
+        };
+        Self::imported_image(plugin, id, repo).await
+    }
+
+    /// The rows of a table of imports, piped in as a list or streamed.
+    fn batch_rows(input: PipelineData, span: Span) -> Result<Vec<Value>, LabeledError> {
+        match input {
+            PipelineData::Value(Value::List { .. }, _) | PipelineData::ListStream(..) => {
+                Ok(input.into_iter().collect())
+            }
+            _ => Err(LabeledError::new("Nothing to import")
+                .with_label("Pass file|URL|- or pipe in a table of imports", span)),
+        }
+    }
+
+    /// Import every row of the table, `parallel` at a time. A failed import
+    /// is reported in its row instead of aborting the batch.
+    fn run_batch(
+        plugin: &<ImageImportCommand as PluginCommand>::Plugin,
+        rt: &tokio::runtime::Runtime,
+        current_path: &String,
+        params: &HashMap<String, Value>,
+        rows: Vec<Value>,
+        parallel: usize,
+        span: Span,
+    ) -> Result<Value, LabeledError> {
+        let jobs = rows.iter().map(|row| {
+            let source = row
+                .get_data_by_key("source")
+                .and_then(|source| source.coerce_string().ok())
+                .unwrap_or_default();
+            (source, Self::batch_job(row, params, current_path))
+        });
+
+        let results = rt.block_on(async {
+            futures_util::stream::iter(jobs)
+                .map(|(source, job)| async move {
+                    let result = match job {
+                        Ok(job) => {
+                            Self::import(plugin, current_path, job, PipelineData::Empty, true).await
+                        }
+                        Err(e) => Err(e),
+                    };
+                    match &result {
+                        Ok(image) => eprintln!("Imported {source}: {}", image.id),
+                        Err(e) => eprintln!("Failed to import {source}: {}", error_message(e)),
+                    }
+                    (source, result)
+                })
+                .buffered(parallel)
+                .collect::<Vec<_>>()
+                .await
+        });
+
+        let rows = results
+            .into_iter()
+            .map(|(source, result)| {
+                let mut record = Record::new();
+                record.insert("source".to_string(), Value::string(source, span));
+                match result {
+                    Ok(image) => {
+                        record.insert("image".to_string(), image.clone_value(span));
+                        record.insert("status".to_string(), Value::string("imported", span));
+                        record.insert("error".to_string(), Value::nothing(span));
+                    }
+                    Err(e) => {
+                        record.insert("image".to_string(), Value::nothing(span));
+                        record.insert("status".to_string(), Value::string("failed", span));
+                        record.insert("error".to_string(), Value::string(error_message(&e), span));
+                    }
+                }
+                Value::record(record, span)
+            })
+            .collect();
+        Ok(Value::list(rows, span))
+    }
+
+    /// Ask for the Dockerfile instructions to apply to the imported image,
+    /// and return them appended to `changes`.
+    fn interactive_changes(
+        engine: &nu_plugin::EngineInterface,
+        span: Span,
+        mut changes: Vec<String>,
+    ) -> Result<Vec<String>, LabeledError> {
+        let prompt = Prompt::new(engine, span)?;
+        eprintln!("Apply Dockerfile instructions to the image, leave empty to skip.");
+        for (keyword, hint, repeated) in INTERACTIVE_INSTRUCTIONS {
+            loop {
+                let question = if *repeated {
+                    format!("{keyword} {hint} (empty to continue): ")
+                } else {
+                    format!("{keyword} {hint}: ")
+                };
+                let answer = prompt.ask_valid(&question, |args| {
+                    parse_instruction(&format!("{keyword} {args}"))
+                        .map(|_| ())
+                        .map_err(|e| e.to_string())
+                })?;
+                if answer.is_empty() {
+                    break;
+                }
+                changes.push(format!("{keyword} {answer}"));
+                if !*repeated {
+                    break;
+                }
+            }
+        }
+
+        if changes.is_empty() {
+            eprintln!("No instruction to apply.");
+        } else {
+            eprintln!("Instructions to apply:");
+            for change in &changes {
+                eprintln!("  {change}");
+            }
+        }
+        if !prompt.confirm("Import the image?", true)? {
+            return Err(LabeledError::new("Import cancelled"));
+        }
+        Ok(changes)
     }
 }
 
+/// The instructions asked in interactive mode, with a hint of their format and
+/// whether they can be given more than once.
+const INTERACTIVE_INSTRUCTIONS: &[(&str, &str, bool)] = &[
+    ("CMD", "[\"executable\", \"param\"] or command param", false),
+    (
+        "ENTRYPOINT",
+        "[\"executable\", \"param\"] or command param",
+        false,
+    ),
+    ("ENV", "key=value", true),
+    ("EXPOSE", "port[/tcp|udp|sctp]", true),
+    ("USER", "user[:group]", false),
+    ("WORKDIR", "/path", false),
+    ("LABEL", "key=value", true),
+    ("VOLUME", "/path or [\"/path\"]", true),
+    ("ONBUILD", "INSTRUCTION arguments", true),
+];
+
 impl PluginCommand for ImageImportCommand {
     type Plugin = NdockerPlugin;
 
@@ -199,8 +741,9 @@ impl PluginCommand for ImageImportCommand {
             .input_output_types(vec![
                 (nu_protocol::Type::Nothing,
                 nu_protocol::Type::Custom("Image".to_string().into_boxed_str())),
-                (nu_protocol::Type::Binary, 
-                nu_protocol::Type::Custom("Image".to_string().into_boxed_str())),]
+                (nu_protocol::Type::Binary,
+                nu_protocol::Type::Custom("Image".to_string().into_boxed_str())),
+                (nu_protocol::Type::table(), nu_protocol::Type::table()),]
             )
             .switch(
                 "interactive",
@@ -213,6 +756,24 @@ impl PluginCommand for ImageImportCommand {
                 "Apply Dockerfile instruction to the created image",
                 Some('c'),
             )
+            .named(
+                "exclude",
+                nu_protocol::Type::List(Box::new(nu_protocol::Type::String)).to_shape(),
+                "Glob patterns of the paths to leave out when importing a directory, relative to it",
+                Some('e'),
+            )
+            .named(
+                "sha256",
+                nu_protocol::Type::String.to_shape(),
+                "Verify the SHA-256 digest of the tarball while uploading it, and abort the import on mismatch",
+                None,
+            )
+            .named(
+                "parallel",
+                nu_protocol::Type::Int.to_shape(),
+                "Number of imports of a table run at the same time, 1 by default",
+                Some('p'),
+            )
             .named(
                 "message",
                 nu_protocol::Type::String.to_shape(),
@@ -225,12 +786,12 @@ impl PluginCommand for ImageImportCommand {
                 "Set the platform for the image, in the format os[/arch[/variant]], for example: linux/amd64/v5",
                 None,
             )
-            .required(
+            .optional(
                 "file|URL|-",
                 nu_protocol::Type::String.to_shape(),
-                "The path to the tarball to import.",
+                "The path to the tarball or the directory to import. Omit it to import a table of {source, repo, message, changes, platform, sha256} on input.",
             )
-            .optional("REPOSITORY[:TAG]", nu_protocol::Type::String.to_shape(), 
+            .optional("REPOSITORY[:TAG]", nu_protocol::Type::String.to_shape(),
                 "The repository and tag to apply to the imported image. If not specified, the image will not be tagged.")
     }
 
@@ -245,81 +806,146 @@ impl PluginCommand for ImageImportCommand {
             nu_protocol::LabeledError::new(format!("Failed to create runtime: {e}"))
         })?;
 
-        let file = call.req::<String>(0)?;
         let current_path = engine.get_current_dir().map_err(|e| {
             nu_protocol::LabeledError::new(format!("Failed to get current directory: {e}"))
         })?;
-        let import_src = ImageImportCommand::get_import_source(&file, &current_path)?;
-
-        let mut options = CreateImageOptionsBuilder::new();
+        let mut params = Self::handle_named_params(call);
+        params.remove("parallel");
+        let parallel = call.get_flag::<i64>("parallel")?;
+        if parallel.is_some_and(|parallel| parallel < 1) {
+            return Err(LabeledError::new("Invalid --parallel")
+                .with_label("Expected at least 1", call.head));
+        }
 
-        if let Ok(repotag) = call.req::<String>(1) {
-            options = options.repo(&repotag);
+        let Some(file) = call.opt::<String>(0)? else {
+            let rows = Self::batch_rows(input, call.head)?;
+            if call.has_flag("interactive")? {
+                return Err(LabeledError::new("Invalid --interactive")
+                    .with_label("Doesn't apply when importing a table", call.head));
+            }
+            let result = Self::run_batch(
+                plugin,
+                &rt,
+                &current_path,
+                &params,
+                rows,
+                parallel.unwrap_or(1) as usize,
+                call.head,
+            )?;
+            return Ok(result.into_pipeline_data());
+        };
+        if parallel.is_some() {
+            return Err(LabeledError::new("Invalid --parallel")
+                .with_label("Only applies when importing a table", call.head));
         }
+        let import_src = ImageImportCommand::get_import_source(&file, &current_path)?;
+        let repotag = call.opt::<String>(1)?;
 
-        let params = Self::handle_named_params(call);
-
-        options = Self::option_get_commit_message(&params, options);
-        options = Self::option_get_platform(&params, options);
-        options = Self::option_get_changes(&params, options);
-
-        let mut id = String::new();
-        let imported_image = rt.block_on(async {
-            match import_src {
-                ImportSrc::File(path) => {
-                    let mut response_stream = 
-                        Self::import_from_file(plugin, &current_path, path, options).await?;
-                    while let Some(response) = response_stream.next().await {
-                        let response = response.map_err(|e| {
-                            nu_protocol::LabeledError::new(format!("Failed to import image from file: {e}"))
-                        })?;
-                        id = response.status.unwrap_or_default();
-                        eprintln!("{}", &id);
-                    }
-                }
-                ImportSrc::Stdin => {
-                    let mut response_stream = 
-                        Self::import_from_stdin(plugin, input, options).await?;
-                    while let Some(response) = response_stream.next().await {
-                        let response = response.map_err(|e| {
-                            nu_protocol::LabeledError::new(format!("Failed to import image from stdin: {e}"))
-                        })?;
-                        id = response.status.unwrap_or_default();
-                        eprintln!("{}", &id);
-                    }
-                }
-                ImportSrc::Url(url) => {
-                    let mut response_stream = 
-                        Self::import_from_url(plugin, url, options).await?;
-                    let mut line_size = 0;
-                    while let Some(response) = response_stream.next().await {
-                        let response = response.map_err(|e| {
-                            nu_protocol::LabeledError::new(format!("Failed to import image from URL: {e}"))
-                        })?;
-                        Self::handle_url_progress(response, &mut id, &mut line_size);
-                    }
-                    eprintln!();
-                }
-            };
-            
-            plugin
-                .docker_socket
-                .list_images(Some(ListImagesOptionsBuilder::new().build()))
-                .await
-                .map_err(|e| {
-                    nu_protocol::LabeledError::new(format!("Failed to list containers: {e}"))
-                })
-        })?;
-        let result = imported_image
-            .into_iter()
-            .filter(|image| image.id == id)
-            .map(|image| Image::new(image))
-            .map(|image| Image::clone_value(&image, call.head.clone()))
-            .collect::<Vec<_>>();
-        Ok(Value::List {
-            vals: result,
-            internal_span: call.head.clone(),
+        if call.has_flag("interactive")? {
+            let changes = params
+                .get("change")
+                .and_then(|changes| changes.as_list().ok())
+                .unwrap_or_default()
+                .iter()
+                .map(|change| change.coerce_string().unwrap_or_default())
+                .collect();
+            let changes = Self::interactive_changes(engine, call.head, changes)?;
+            params.insert(
+                "change".to_string(),
+                Value::list(
+                    changes
+                        .into_iter()
+                        .map(|change| Value::string(change, call.head))
+                        .collect(),
+                    call.head,
+                ),
+            );
         }
-        .into_pipeline_data())
+        let job = Self::import_job(&params, import_src, repotag.as_deref(), call.head)?;
+
+        let image = rt.block_on(Self::import(plugin, &current_path, job, input, false))?;
+        Ok(image.clone_value(call.head).into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<nu_protocol::Example<'_>> {
+        vec![
+            Example {
+                description: "Import a tarball and tag the image",
+                example: "ndocker image import rootfs.tar.gz alpine-rootfs:latest",
+                result: None,
+            },
+            Example {
+                description: "Import a directory, leaving out the caches",
+                example: "ndocker image import ./rootfs rootfs:dev --exclude [var/cache tmp/*]",
+                result: None,
+            },
+            Example {
+                description: "Import a table of tarballs, two at a time, and show the failed ones",
+                example: "[[source repo changes]; [base.tar base:nightly ['ENV LANG=C.UTF-8']] [tools.tar.zst tools:nightly []]] | ndocker image import --parallel 2 | where status == failed",
+                result: None,
+            },
+        ]
+    }
+}
+
+/// The message of an error along with its labels, to report it in a table.
+fn error_message(error: &LabeledError) -> String {
+    std::iter::once(error.msg.as_str())
+        .chain(error.labels.iter().map(|label| label.text.as_str()))
+        .collect::<Vec<_>>()
+        .join(": ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nu_protocol::{ByteStream, ListStream, Signals};
+
+    fn row(source: &str) -> Value {
+        let mut record = Record::new();
+        record.insert("source".to_string(), Value::test_string(source));
+        Value::test_record(record)
+    }
+
+    #[test]
+    fn batch_rows_accepts_lists_and_streams() {
+        let span = Span::test_data();
+        let rows = vec![row("base.tar"), row("app.tar.gz")];
+
+        let list = Value::list(rows.clone(), span).into_pipeline_data();
+        assert_eq!(ImageImportCommand::batch_rows(list, span).unwrap(), rows);
+
+        let stream = PipelineData::ListStream(
+            ListStream::new(rows.clone().into_iter(), span, Signals::empty()),
+            None,
+        );
+        assert_eq!(ImageImportCommand::batch_rows(stream, span).unwrap(), rows);
+    }
+
+    #[test]
+    fn batch_rows_rejects_other_input() {
+        let span = Span::test_data();
+        assert!(ImageImportCommand::batch_rows(PipelineData::Empty, span).is_err());
+        let bytes = PipelineData::ByteStream(
+            ByteStream::read_binary(vec![0; 512], span, Signals::empty()),
+            None,
+        );
+        assert!(ImageImportCommand::batch_rows(bytes, span).is_err());
+        let record = row("base.tar").into_pipeline_data();
+        assert!(ImageImportCommand::batch_rows(record, span).is_err());
+    }
+
+    #[test]
+    fn parse_image_id_reads_the_digest_of_the_status() {
+        let digest = "a".repeat(64);
+        assert_eq!(
+            ImageImportCommand::parse_image_id(&format!("sha256:{}", digest.to_uppercase())),
+            Some(format!("sha256:{digest}"))
+        );
+        assert_eq!(
+            ImageImportCommand::parse_image_id(&digest),
+            Some(format!("sha256:{digest}"))
+        );
+        assert_eq!(ImageImportCommand::parse_image_id("Importing"), None);
     }
 }
@@ -1,12 +1,32 @@
 //! This module is for command `ndocker image inspect`.
 
 use crate::NdockerPlugin;
+use crate::commands::image::{INSPECT_CONCURRENCY, ImageDetails, references_from_input};
 
-use nu_plugin::{EvaluatedCall, PluginCommand};
-use nu_protocol::{IntoPipelineData, Value};
+use nu_plugin::PluginCommand;
+use nu_protocol::{CustomValue, Example, IntoPipelineData, LabeledError, ShellError, Span, Value};
+
+use bollard::secret::ImageInspect;
+
+use futures_util::stream::StreamExt;
 
 pub struct ImageInspectCommand;
 
+impl ImageInspectCommand {
+    /// The row of an inspected image, or the error pointing at the reference
+    /// which failed, so that the other images are still shown.
+    fn row_value(
+        result: Result<ImageInspect, LabeledError>,
+        reference_span: Span,
+        span: Span,
+    ) -> Value {
+        match result {
+            Ok(image_inspect) => ImageDetails::new(image_inspect).clone_value(span),
+            Err(e) => Value::error(ShellError::from(e), reference_span),
+        }
+    }
+}
+
 impl PluginCommand for ImageInspectCommand {
     type Plugin = NdockerPlugin;
 
@@ -17,37 +37,93 @@ impl PluginCommand for ImageInspectCommand {
     fn signature(&self) -> nu_protocol::Signature {
         nu_protocol::Signature::build("ndocker image inspect")
             .input_output_types(vec![
-                (nu_protocol::Type::Nothing, nu_protocol::Type::record()),
+                (
+                    nu_protocol::Type::Nothing,
+                    nu_protocol::Type::Custom("ImageDetails".to_string().into_boxed_str()),
+                ),
+                (nu_protocol::Type::Nothing, nu_protocol::Type::table()),
                 (nu_protocol::Type::Nothing, nu_protocol::Type::String),
+                (
+                    nu_protocol::Type::Custom("Image".to_string().into_boxed_str()),
+                    nu_protocol::Type::table(),
+                ),
+                (nu_protocol::Type::table(), nu_protocol::Type::table()),
+                (
+                    nu_protocol::Type::List(Box::new(nu_protocol::Type::Any)),
+                    nu_protocol::Type::table(),
+                ),
+                (nu_protocol::Type::String, nu_protocol::Type::table()),
+                (nu_protocol::Type::Any, nu_protocol::Type::String),
             ])
-            .switch("string", "Show information in plain string", Some('s'))
-            .required(
+            .switch(
+                "string",
+                "Show the raw JSON returned by the daemon",
+                Some('s'),
+            )
+            .rest(
                 "IMAGE",
                 nu_protocol::Type::String.to_shape(),
-                "The ID or name of the image to inspect.",
+                "The IDs or names of the images to inspect, or pipe the images in.",
             )
     }
 
     fn description(&self) -> &str {
-        "Inspect a Docker image and show its detailed information."
+        "Inspect Docker images and show their detailed information."
+    }
+
+    fn extra_description(&self) -> &str {
+        "When several images are inspected, an image which cannot be inspected gets an error pointing at its reference in place of its row, the other images are still inspected."
     }
 
     fn run(
         &self,
         plugin: &Self::Plugin,
-        engine: &nu_plugin::EngineInterface,
+        _engine: &nu_plugin::EngineInterface,
         call: &nu_plugin::EvaluatedCall,
-        _input: nu_protocol::PipelineData,
+        input: nu_protocol::PipelineData,
     ) -> Result<nu_protocol::PipelineData, nu_protocol::LabeledError> {
         let rt = tokio::runtime::Runtime::new().map_err(|e| {
             nu_protocol::LabeledError::new(format!("Failed to create runtime: {e}"))
         })?;
-        let image_id: String = call.req(0)?;
-        let image_inspect = rt
-            .block_on(plugin.docker_socket.inspect_image(&image_id))
-            .map_err(|e| {
-                nu_protocol::LabeledError::new(format!("Failed to inspect Docker image: {e}"))
-            })?;
+
+        let mut images = call
+            .positional
+            .iter()
+            .map(|value| {
+                value
+                    .coerce_string()
+                    .map(|image| (image, value.span()))
+                    .map_err(|e| LabeledError::new(format!("Invalid image: {e}")))
+            })
+            .collect::<Result<Vec<(String, Span)>, LabeledError>>()?;
+        // A single image given as argument is shown as is, otherwise as a table.
+        let single = images.len() == 1;
+        let piped = references_from_input(input)?;
+        let single = single && piped.is_empty();
+        images.extend(piped);
+        if images.is_empty() {
+            return Err(LabeledError::new("No image to inspect")
+                .with_label("Pass IMAGE or pipe the images in", call.head));
+        }
+
+        // Each image is inspected on its own, a failure only affects its row.
+        let image_inspects = rt.block_on(async {
+            futures_util::stream::iter(images)
+                .map(|(image, span)| async move {
+                    let result = plugin
+                        .docker_socket
+                        .inspect_image(&image)
+                        .await
+                        .map_err(|e| {
+                            LabeledError::new(format!("Failed to inspect Docker image {image}"))
+                                .with_label(e.to_string(), span)
+                        });
+                    (result, span)
+                })
+                .buffered(INSPECT_CONCURRENCY)
+                .collect::<Vec<_>>()
+                .await
+        });
 
         if let Some(timeout) = plugin.timeout {
             rt.shutdown_timeout(timeout);
@@ -58,23 +134,82 @@ impl PluginCommand for ImageInspectCommand {
         }
 
         if call.has_flag("string") == Ok(true) {
-            let result = serde_json::to_string_pretty(&image_inspect)
-                .map_err(|e| nu_protocol::LabeledError::new(format!("Failed to serialize: {e}")))?;
-            Ok(Value::string(result, call.head.clone()).into_pipeline_data())
+            let image_inspects = image_inspects
+                .into_iter()
+                .map(|(result, _)| result)
+                .collect::<Result<Vec<_>, LabeledError>>()?;
+            let result = if single {
+                serde_json::to_string_pretty(&image_inspects[0])
+            } else {
+                serde_json::to_string_pretty(&image_inspects)
+            }
+            .map_err(|e| nu_protocol::LabeledError::new(format!("Failed to serialize: {e}")))?;
+            Ok(Value::string(result, call.head).into_pipeline_data())
+        } else if single {
+            let (result, _) = image_inspects.into_iter().next().unwrap();
+            Ok(ImageDetails::new(result?)
+                .clone_value(call.head)
+                .into_pipeline_data())
         } else {
-            let result = serde_json::to_string(&image_inspect)
-                .map_err(|e| nu_protocol::LabeledError::new(format!("Failed to serialize: {e}")))?;
-            let from_json_operation_id = engine.find_decl("from json")?.ok_or_else(|| {
-                nu_protocol::LabeledError::new("Failed to find 'from json' operation".to_string())
-            })?;
-            let reuslt = engine.call_decl(
-                from_json_operation_id,
-                EvaluatedCall::new(call.head),
-                Value::string(result, call.head.clone()).into_pipeline_data(),
-                true,
-                false,
-            )?;
-            Ok(reuslt)
+            let details = image_inspects
+                .into_iter()
+                .map(|(result, span)| Self::row_value(result, span, call.head))
+                .collect();
+            Ok(Value::list(details, call.head).into_pipeline_data())
         }
     }
+
+    fn examples(&self) -> Vec<nu_protocol::Example<'_>> {
+        vec![
+            Example {
+                description: "Show the environment variables of an image",
+                example: "(ndocker image inspect rust:1.84.0).config.env",
+                result: None,
+            },
+            Example {
+                description: "Inspect the images created during the last week",
+                example: "ndocker images | where created > ((date now) - 1wk) | ndocker image inspect",
+                result: None,
+            },
+            Example {
+                description: "Show the raw JSON returned by the daemon",
+                example: "ndocker image inspect rust:1.84.0 --string",
+                result: None,
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn row_value_points_at_the_failing_reference() {
+        let reference_span = Span::new(20, 26);
+        let error = LabeledError::new("Failed to inspect Docker image alpine")
+            .with_label("No such image: alpine:latest", reference_span);
+        let value = ImageInspectCommand::row_value(Err(error), reference_span, Span::test_data());
+        let Value::Error { error, .. } = &value else {
+            panic!("expected an error, got {value:?}");
+        };
+        assert_eq!(value.span(), reference_span);
+        assert!(
+            error
+                .to_string()
+                .contains("Failed to inspect Docker image alpine")
+        );
+    }
+
+    #[test]
+    fn row_value_shows_the_details() {
+        let image_inspect = ImageInspect {
+            id: Some("sha256:aded1e1a5b37".to_string()),
+            ..Default::default()
+        };
+        let value =
+            ImageInspectCommand::row_value(Ok(image_inspect), Span::new(20, 26), Span::test_data());
+        assert!(matches!(value, Value::Custom { .. }));
+        assert_eq!(value.span(), Span::test_data());
+    }
 }
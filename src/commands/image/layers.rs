@@ -0,0 +1,275 @@
+//! This module is for command `ndocker image layers`.
+
+use crate::NdockerPlugin;
+
+use nu_plugin::PluginCommand;
+use nu_protocol::{Example, Filesize, IntoPipelineData, LabeledError, Record, Span, Value};
+
+use bollard::query_parameters::ListImagesOptionsBuilder;
+use bollard::secret::HistoryResponseItem;
+use chrono::DateTime;
+
+pub struct ImageLayersCommand;
+
+impl ImageLayersCommand {
+    /// Pair the layers of the root filesystem, oldest first, with the history
+    /// steps that created them. The steps that only change the configuration
+    /// don't create a layer, they are recognized by their size of 0 and the
+    /// `#(nop)` marker of the classic builder.
+    fn pair_layers(
+        layers: &[String],
+        mut history: Vec<HistoryResponseItem>,
+    ) -> Vec<(String, Option<HistoryResponseItem>)> {
+        history.reverse();
+        let creates_layer = |step: &HistoryResponseItem| {
+            step.size > 0 || !(step.created_by.is_empty() || step.created_by.contains("#(nop)"))
+        };
+        let mut steps = history
+            .iter()
+            .filter(|step| step.size > 0)
+            .cloned()
+            .collect::<Vec<_>>();
+        if steps.len() != layers.len() {
+            steps = history.into_iter().filter(creates_layer).collect();
+        }
+        if steps.len() != layers.len() {
+            // The history doesn't match the layers, e.g. for an imported image.
+            return layers.iter().map(|layer| (layer.clone(), None)).collect();
+        }
+        layers
+            .iter()
+            .cloned()
+            .zip(steps.into_iter().map(Some))
+            .collect()
+    }
+
+    /// Whether the layer, of `size` bytes over `below` bytes of older layers,
+    /// is used by other images as well.
+    ///
+    /// A layer is only shared along with the layers below it, so the shared
+    /// layers are the oldest ones, adding up to the `shared_size` the daemon
+    /// computes, -1 when unknown. An empty layer at the limit is taken as not
+    /// shared.
+    fn is_shared(below: i64, size: i64, shared_size: i64) -> bool {
+        let above = below + size;
+        above < shared_size || (above == shared_size && size > 0)
+    }
+
+    fn layer_value(
+        layer: String,
+        step: Option<HistoryResponseItem>,
+        cumulative_size: i64,
+        shared: bool,
+        span: Span,
+    ) -> Value {
+        let mut base = Record::new();
+        base.insert("layer".to_string(), Value::string(layer, span));
+        match step {
+            Some(step) => {
+                base.insert(
+                    "created".to_string(),
+                    Value::date(
+                        DateTime::from_timestamp(step.created, 0)
+                            .unwrap_or_default()
+                            .fixed_offset(),
+                        span,
+                    ),
+                );
+                base.insert(
+                    "created_by".to_string(),
+                    Value::string(step.created_by, span),
+                );
+                base.insert(
+                    "size".to_string(),
+                    Value::filesize(Filesize::new(step.size), span),
+                );
+            }
+            None => {
+                base.insert("created".to_string(), Value::nothing(span));
+                base.insert("created_by".to_string(), Value::nothing(span));
+                base.insert("size".to_string(), Value::nothing(span));
+            }
+        }
+        base.insert(
+            "cumulative_size".to_string(),
+            Value::filesize(Filesize::new(cumulative_size), span),
+        );
+        base.insert("shared".to_string(), Value::bool(shared, span));
+        Value::record(base, span)
+    }
+}
+
+impl PluginCommand for ImageLayersCommand {
+    type Plugin = NdockerPlugin;
+
+    fn name(&self) -> &str {
+        "ndocker image layers"
+    }
+
+    fn description(&self) -> &str {
+        "List the layers of a Docker image with the step that created them and their size."
+    }
+
+    fn signature(&self) -> nu_protocol::Signature {
+        nu_protocol::Signature::build("ndocker image layers")
+            .input_output_types(vec![(
+                nu_protocol::Type::Nothing,
+                nu_protocol::Type::table(),
+            )])
+            .required(
+                "IMAGE",
+                nu_protocol::Type::String.to_shape(),
+                "The ID or name of the image to show the layers of.",
+            )
+    }
+
+    fn run(
+        &self,
+        plugin: &Self::Plugin,
+        _engine: &nu_plugin::EngineInterface,
+        call: &nu_plugin::EvaluatedCall,
+        _input: nu_protocol::PipelineData,
+    ) -> Result<nu_protocol::PipelineData, nu_protocol::LabeledError> {
+        let rt = tokio::runtime::Runtime::new()
+            .map_err(|e| LabeledError::new(format!("Failed to create runtime: {e}")))?;
+        let image: String = call.req(0)?;
+
+        let (image_inspect, history, shared_size) = rt.block_on(async {
+            let image_inspect = plugin
+                .docker_socket
+                .inspect_image(&image)
+                .await
+                .map_err(|e| LabeledError::new(format!("Failed to inspect Docker image: {e}")))?;
+            let history = plugin
+                .docker_socket
+                .image_history(&image)
+                .await
+                .map_err(|e| LabeledError::new(format!("Failed to get image history: {e}")))?;
+
+            // The daemon computes the size shared with the other images when
+            // listing them, the dangling intermediate images don't share any.
+            let id = image_inspect.id.clone().unwrap_or_default();
+            let shared_size = plugin
+                .docker_socket
+                .list_images(Some(
+                    ListImagesOptionsBuilder::new().shared_size(true).build(),
+                ))
+                .await
+                .map_err(|e| LabeledError::new(format!("Failed to list Docker images: {e}")))?
+                .into_iter()
+                .find(|summary| summary.id == id)
+                .map(|summary| summary.shared_size)
+                .unwrap_or(-1);
+            Ok::<_, LabeledError>((image_inspect, history, shared_size))
+        })?;
+
+        if let Some(timeout) = plugin.timeout {
+            rt.shutdown_timeout(timeout);
+            return Err(LabeledError::new(format!(
+                "Timeout: Operation time exceeded {} seconds",
+                timeout.as_secs()
+            )));
+        }
+
+        let span = call.head;
+        let layers = image_inspect
+            .root_fs
+            .and_then(|root_fs| root_fs.layers)
+            .unwrap_or_default();
+        let mut cumulative_size = 0;
+        let result = Self::pair_layers(&layers, history)
+            .into_iter()
+            .map(|(layer, step)| {
+                let size = step.as_ref().map(|step| step.size);
+                let shared =
+                    size.is_some_and(|size| Self::is_shared(cumulative_size, size, shared_size));
+                cumulative_size += size.unwrap_or_default();
+                Self::layer_value(layer, step, cumulative_size, shared, span)
+            })
+            .collect();
+        Ok(Value::list(result, span).into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<nu_protocol::Example<'_>> {
+        vec![
+            Example {
+                description: "Show the layers of an image",
+                example: "ndocker image layers rust:1.84.0",
+                result: None,
+            },
+            Example {
+                description: "Find the steps that made an image big",
+                example: "ndocker image layers rust:1.84.0 | where not shared | sort-by size --reverse | first 3",
+                result: None,
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(created_by: &str, size: i64) -> HistoryResponseItem {
+        HistoryResponseItem {
+            created_by: created_by.to_string(),
+            size,
+            ..Default::default()
+        }
+    }
+
+    fn layers(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn is_shared_marks_the_oldest_layers() {
+        // Layers of 100, 0, 50 and 30 bytes, the first three being shared.
+        assert!(ImageLayersCommand::is_shared(0, 100, 150));
+        assert!(ImageLayersCommand::is_shared(100, 0, 150));
+        assert!(ImageLayersCommand::is_shared(100, 50, 150));
+        assert!(!ImageLayersCommand::is_shared(150, 30, 150));
+        // An empty layer right above the shared ones.
+        assert!(!ImageLayersCommand::is_shared(150, 0, 150));
+    }
+
+    #[test]
+    fn is_shared_without_shared_size() {
+        assert!(!ImageLayersCommand::is_shared(0, 100, 0));
+        assert!(!ImageLayersCommand::is_shared(0, 0, 0));
+        assert!(!ImageLayersCommand::is_shared(0, 100, -1));
+    }
+
+    #[test]
+    fn pair_layers_skips_the_configuration_steps() {
+        // The history is newest first.
+        let history = vec![
+            step("/bin/sh -c #(nop)  CMD [\"sh\"]", 0),
+            step("RUN /bin/sh -c apk add curl # buildkit", 5),
+            step("/bin/sh -c #(nop) ADD file:abc in / ", 10),
+        ];
+        let paired = ImageLayersCommand::pair_layers(&layers(&["a", "b"]), history);
+        let sizes = paired
+            .iter()
+            .map(|(layer, step)| (layer.as_str(), step.as_ref().map(|step| step.size)))
+            .collect::<Vec<_>>();
+        assert_eq!(sizes, vec![("a", Some(10)), ("b", Some(5))]);
+    }
+
+    #[test]
+    fn pair_layers_keeps_the_empty_layers() {
+        let history = vec![
+            step("RUN /bin/sh -c touch /tmp # buildkit", 0),
+            step("/bin/sh -c #(nop) ADD file:abc in / ", 10),
+        ];
+        let paired = ImageLayersCommand::pair_layers(&layers(&["a", "b"]), history);
+        assert!(paired.iter().all(|(_, step)| step.is_some()));
+    }
+
+    #[test]
+    fn pair_layers_without_matching_history() {
+        let paired =
+            ImageLayersCommand::pair_layers(&layers(&["a", "b"]), vec![step("Imported", 10)]);
+        assert!(paired.iter().all(|(_, step)| step.is_none()));
+    }
+}
@@ -0,0 +1,177 @@
+//! This module is for command `ndocker image load`.
+
+use crate::NdockerPlugin;
+use crate::commands::image::Image;
+use crate::commands::image::progress::ProgressPrinter;
+use crate::utils::file::{check_file_exists, read_byte_stream, read_file_stream};
+
+use nu_plugin::PluginCommand;
+use nu_protocol::{CustomValue, Example, IntoPipelineData, LabeledError, PipelineData, Value};
+
+use bollard::query_parameters::ImportImageOptionsBuilder;
+use bollard::secret::BuildInfo;
+
+use futures_util::stream::{Stream, StreamExt};
+
+pub struct ImageLoadCommand;
+
+impl ImageLoadCommand {
+    /// Parse the messages sent by the daemon for every loaded image,
+    /// e.g. `Loaded image: rust:1.84.0` or `Loaded image ID: sha256:8daff9993116`.
+    fn parse_loaded_image(message: &str) -> Option<String> {
+        message
+            .trim()
+            .strip_prefix("Loaded image: ")
+            .or_else(|| message.trim().strip_prefix("Loaded image ID: "))
+            .map(|image| image.to_string())
+    }
+
+    async fn handle_load_stream(
+        mut response_stream: impl Stream<Item = Result<BuildInfo, bollard::errors::Error>> + Unpin,
+        progress: &mut ProgressPrinter,
+    ) -> Result<Vec<String>, LabeledError> {
+        let mut loaded = Vec::new();
+        while let Some(response) = response_stream.next().await {
+            let response =
+                response.map_err(|e| LabeledError::new(format!("Failed to load image: {e}")))?;
+            if let Some(error) = response.error {
+                return Err(LabeledError::new(format!("Failed to load image: {error}")));
+            }
+            match response
+                .stream
+                .as_deref()
+                .and_then(Self::parse_loaded_image)
+            {
+                Some(image) => loaded.push(image),
+                None => progress.update_line(response.status, response.progress),
+            }
+        }
+        progress.finish();
+        Ok(loaded)
+    }
+}
+
+impl PluginCommand for ImageLoadCommand {
+    type Plugin = NdockerPlugin;
+
+    fn name(&self) -> &str {
+        "ndocker image load"
+    }
+
+    fn description(&self) -> &str {
+        "Load images from a tar archive created by `ndocker image save`."
+    }
+
+    fn signature(&self) -> nu_protocol::Signature {
+        nu_protocol::Signature::build("ndocker image load")
+            .input_output_types(vec![
+                (nu_protocol::Type::Nothing, nu_protocol::Type::table()),
+                (nu_protocol::Type::Binary, nu_protocol::Type::table()),
+            ])
+            .switch("quiet", "Suppress the progress output", Some('q'))
+            .named(
+                "input",
+                nu_protocol::Type::String.to_shape(),
+                "Read from a tar archive file, instead of the binary input",
+                Some('i'),
+            )
+            .named(
+                "platform",
+                nu_protocol::Type::String.to_shape(),
+                "Load only the given platform variant, in the format os[/arch[/variant]], for example: linux/amd64/v5",
+                None,
+            )
+    }
+
+    fn run(
+        &self,
+        plugin: &Self::Plugin,
+        engine: &nu_plugin::EngineInterface,
+        call: &nu_plugin::EvaluatedCall,
+        input: nu_protocol::PipelineData,
+    ) -> Result<nu_protocol::PipelineData, nu_protocol::LabeledError> {
+        let rt = tokio::runtime::Runtime::new()
+            .map_err(|e| LabeledError::new(format!("Failed to create runtime: {e}")))?;
+
+        let quiet = call.has_flag("quiet")?;
+        let mut options = ImportImageOptionsBuilder::new().quiet(quiet);
+        if let Some(platform) = call.get_flag::<String>("platform")? {
+            options = options.platform(&platform);
+        }
+        let options = options.build();
+        let mut progress = ProgressPrinter::new(quiet);
+
+        let images = rt.block_on(async {
+            let (stream, reader, source) = if let Some(path) = call.get_flag::<String>("input")? {
+                let current_path = engine.get_current_dir().map_err(|e| {
+                    LabeledError::new(format!("Failed to get current directory: {e}"))
+                })?;
+                check_file_exists(&current_path, &path)
+                    .map_err(|e| LabeledError::new(format!("{e}")))?;
+                let (file_stream, reader) = read_file_stream(current_path, path)
+                    .map_err(|e| LabeledError::new(format!("Failed to read file stream: {e}")))?;
+                (file_stream.boxed(), reader, "file")
+            } else if let PipelineData::ByteStream(stream, _) = input {
+                let (byte_stream, reader) = read_byte_stream(stream)
+                    .map_err(|e| LabeledError::new(format!("Failed to read input: {e}")))?;
+                (byte_stream.boxed(), reader, "input")
+            } else {
+                return Err(LabeledError::new("Expected binary input or --input")
+                    .with_label("Pipe in a tar archive or pass --input", call.head));
+            };
+
+            let response_stream = plugin
+                .docker_socket
+                .import_image_stream(options, stream, None);
+            let loaded = Self::handle_load_stream(Box::pin(response_stream), &mut progress).await;
+            // A failed read truncates the upload, so report it before the daemon
+            // error. The reader is only waited for once the daemon consumed the
+            // whole input, or if it already stopped.
+            if loaded.is_ok() || reader.is_finished() {
+                reader
+                    .join()
+                    .map_err(|_| LabeledError::new(format!("Failed to read {source}")))?
+                    .map_err(|e| LabeledError::new(format!("Failed to read {source}: {e}")))?;
+            }
+            let loaded = loaded?;
+
+            let mut images = Vec::new();
+            for image in loaded {
+                images.push(Image::from_reference(&plugin.docker_socket, &image).await?);
+            }
+            Ok(images)
+        })?;
+
+        if let Some(timeout) = plugin.timeout {
+            rt.shutdown_timeout(timeout);
+            return Err(LabeledError::new(format!(
+                "Timeout: Operation time exceeded {} seconds",
+                timeout.as_secs()
+            )));
+        }
+
+        Ok(Value::list(
+            images
+                .into_iter()
+                .map(|image| image.clone_value(call.head))
+                .collect(),
+            call.head,
+        )
+        .into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<nu_protocol::Example<'_>> {
+        vec![
+            Example {
+                description: "Load images from a tar archive",
+                example: "ndocker image load -i rust.tar",
+                result: None,
+            },
+            Example {
+                description: "Load images from a compressed archive",
+                example: "open --raw rust.tar.gz | ^gunzip | ndocker image load",
+                result: None,
+            },
+        ]
+    }
+}
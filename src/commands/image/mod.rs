@@ -1,20 +1,87 @@
+pub mod build;
+pub mod details_type;
+pub mod diff;
+pub mod files;
 pub mod history;
 pub mod history_type;
 pub mod images;
 pub mod import;
 pub mod inspect;
+pub mod layers;
+pub mod load;
+pub mod progress;
+pub mod prune;
+pub mod pull;
+pub mod push;
+pub mod rm;
+pub mod save;
+pub mod search;
+pub mod tag;
 
+pub use details_type::ImageDetails;
 pub use history_type::ImageHistory;
 
 use crate::commands::shorten_id;
 
 use std::any::Any;
 
-use bollard::secret::ImageSummary;
+use bollard::Docker;
+use bollard::secret::{ImageInspect, ImageSummary};
 use chrono::{DateTime, FixedOffset};
-use nu_protocol::{CustomValue, Record, ShellError, Span, Value};
+use nu_protocol::{CustomValue, LabeledError, PipelineData, Record, ShellError, Span, Value};
 use serde::{Deserialize, Serialize};
 
+/// Number of images inspected at the same time.
+pub const INSPECT_CONCURRENCY: usize = 8;
+
+/// Split a reference like `localhost:5000/rust:1.84.0` into its name and tag.
+/// Digests are kept in the name.
+pub fn split_tag(reference: &str) -> (&str, Option<&str>) {
+    if reference.contains('@') {
+        return (reference, None);
+    }
+    match reference.rsplit_once(':') {
+        Some((name, tag)) if !tag.contains('/') => (name, Some(tag)),
+        _ => (reference, None),
+    }
+}
+
+/// Collect the image references piped into a command.
+/// Accepts `Image` custom values, strings, and records with an `id` column,
+/// like the ones returned by `ndocker images`.
+pub fn references_from_input(input: PipelineData) -> Result<Vec<(String, Span)>, LabeledError> {
+    input
+        .into_iter()
+        .map(|value| {
+            let span = value.span();
+            match &value {
+                Value::Custom { val, .. } => val
+                    .as_any()
+                    .downcast_ref::<Image>()
+                    .map(|image| (image.id.clone(), span))
+                    .ok_or_else(|| {
+                        LabeledError::new("Invalid input")
+                            .with_label(format!("Expected Image, found {}", val.type_name()), span)
+                    }),
+                Value::String { val, .. } => Ok((val.clone(), span)),
+                Value::Record { val, .. } => val
+                    .get("id")
+                    .and_then(|id| id.as_str().ok())
+                    .map(|id| (id.to_string(), span))
+                    .ok_or_else(|| {
+                        LabeledError::new("Invalid input")
+                            .with_label("Expected a record with an \"id\" column", span)
+                    }),
+                Value::Error { error, .. } => Err(LabeledError::from_diagnostic(error.as_ref())),
+                _ => Err(LabeledError::new("Invalid input").with_label(
+                    format!("Expected Image or string, found {}", value.get_type()),
+                    span,
+                )),
+            }
+        })
+        .collect()
+}
+
 /// This struct contains the information about an image.
 /// It is also a custom value that can be used in NuShell.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,6 +89,7 @@ pub struct Image {
     pub id: String,
     pub parent_id: String,
     pub repo_tags: Vec<String>,
+    pub repo_digests: Vec<String>,
     pub created: DateTime<FixedOffset>,
     pub size: i64,
     pub shared_size: i64,
@@ -34,6 +102,7 @@ impl Image {
             id: image_summary.id,
             parent_id: image_summary.parent_id,
             repo_tags: image_summary.repo_tags,
+            repo_digests: image_summary.repo_digests,
             created: DateTime::from_timestamp(image_summary.created, 0)
                 .unwrap_or_default()
                 .fixed_offset(),
@@ -43,34 +112,65 @@ impl Image {
         }
     }
 
-    pub fn short_version(&self, span: Span) -> Value {
+    /// Build an image from the answer of `inspect_image`. The daemon only
+    /// computes the shared size and the containers when listing images, so
+    /// they are reported as not computed, like `list_images` does.
+    pub fn from_inspect(image_inspect: ImageInspect) -> Self {
+        Self {
+            id: image_inspect.id.unwrap_or_default(),
+            parent_id: image_inspect.parent.unwrap_or_default(),
+            repo_tags: image_inspect.repo_tags.unwrap_or_default(),
+            repo_digests: image_inspect.repo_digests.unwrap_or_default(),
+            created: image_inspect
+                .created
+                .map(|created| created.fixed_offset())
+                .unwrap_or_default(),
+            size: image_inspect.size.unwrap_or_default(),
+            shared_size: -1,
+            containers: -1,
+        }
+    }
+
+    /// Find the image that `reference` (a name, tag, digest or id) points to.
+    pub async fn from_reference(docker: &Docker, reference: &str) -> Result<Self, LabeledError> {
+        docker
+            .inspect_image(reference)
+            .await
+            .map(Image::from_inspect)
+            .map_err(|e| LabeledError::new(format!("Failed to inspect Docker image: {e}")))
+    }
+
+    pub fn short_version(&self, span: Span, digests: bool) -> Value {
         let mut base = Record::new();
-        self.base_add_repo_tags(&mut base, span.clone());
-        self.base_add_created(&mut base, span.clone());
-        self.base_add_size(&mut base, span.clone());
+        self.base_add_repo_tags(&mut base, span);
+        if digests {
+            self.base_add_repo_digests(&mut base, span);
+        }
+        self.base_add_created(&mut base, span);
+        self.base_add_size(&mut base, span);
         Value::record(base, span)
     }
 
-    pub fn standard_version(&self, span: Span) -> Value {
+    pub fn standard_version(&self, span: Span, digests: bool) -> Value {
         let mut base = Record::new();
-        self.base_add_id(&mut base, span.clone());
-        self.base_add_repo_tags(&mut base, span.clone());
-        self.base_add_created(&mut base, span.clone());
-        self.base_add_size(&mut base, span.clone());
+        self.base_add_id(&mut base, span);
+        self.base_add_repo_tags(&mut base, span);
+        if digests {
+            self.base_add_repo_digests(&mut base, span);
+        }
+        self.base_add_created(&mut base, span);
+        self.base_add_size(&mut base, span);
         Value::record(base, span)
     }
 
     pub fn base_add_id(&self, base: &mut Record, span: Span) {
         let short_id = shorten_id(&self.id);
-        base.insert("id".to_string(), Value::string(&short_id, span.clone()));
+        base.insert("id".to_string(), Value::string(&short_id, span));
     }
 
     pub fn base_add_parent_id(&self, base: &mut Record, span: Span) {
         let short_id = shorten_id(&self.parent_id);
-        base.insert(
-            "parent_id".to_string(),
-            Value::string(&short_id, span.clone()),
-        );
+        base.insert("parent_id".to_string(), Value::string(&short_id, span));
     }
 
     pub fn base_add_repo_tags(&self, base: &mut Record, span: Span) {
@@ -80,9 +180,23 @@ impl Image {
                 vals: self
                     .repo_tags
                     .iter()
-                    .map(|repo_tag| Value::string(repo_tag, span.clone()))
+                    .map(|repo_tag| Value::string(repo_tag, span))
+                    .collect::<Vec<_>>(),
+                internal_span: span,
+            },
+        );
+    }
+
+    pub fn base_add_repo_digests(&self, base: &mut Record, span: Span) {
+        base.insert(
+            "repodigests".to_string(),
+            Value::List {
+                vals: self
+                    .repo_digests
+                    .iter()
+                    .map(|repo_digest| Value::string(repo_digest, span))
                     .collect::<Vec<_>>(),
-                internal_span: span.clone(),
+                internal_span: span,
             },
         );
     }
@@ -92,7 +206,7 @@ impl Image {
             "created".to_string(),
             Value::Date {
                 val: self.created,
-                internal_span: span.clone(),
+                internal_span: span,
             },
         );
     }
@@ -100,22 +214,19 @@ impl Image {
     pub fn base_add_size(&self, base: &mut Record, span: Span) {
         base.insert(
             "size".to_string(),
-            Value::filesize(nu_protocol::Filesize::new(self.size), span.clone()),
+            Value::filesize(nu_protocol::Filesize::new(self.size), span),
         );
     }
 
     pub fn base_add_shared_size(&self, base: &mut Record, span: Span) {
         base.insert(
             "shared_size".to_string(),
-            Value::filesize(nu_protocol::Filesize::new(self.shared_size), span.clone()),
+            Value::filesize(nu_protocol::Filesize::new(self.shared_size), span),
         );
     }
 
     pub fn base_add_containers(&self, base: &mut Record, span: Span) {
-        base.insert(
-            "containers".to_string(),
-            Value::int(self.containers, span.clone()),
-        );
+        base.insert("containers".to_string(), Value::int(self.containers, span));
     }
 }
 
@@ -131,13 +242,14 @@ impl CustomValue for Image {
 
     fn to_base_value(&self, span: Span) -> Result<Value, nu_protocol::ShellError> {
         let mut record = nu_protocol::Record::new();
-        self.base_add_id(&mut record, span.clone());
-        self.base_add_parent_id(&mut record, span.clone());
-        self.base_add_repo_tags(&mut record, span.clone());
-        self.base_add_created(&mut record, span.clone());
-        self.base_add_size(&mut record, span.clone());
-        self.base_add_shared_size(&mut record, span.clone());
-        self.base_add_containers(&mut record, span.clone());
+        self.base_add_id(&mut record, span);
+        self.base_add_parent_id(&mut record, span);
+        self.base_add_repo_tags(&mut record, span);
+        self.base_add_repo_digests(&mut record, span);
+        self.base_add_created(&mut record, span);
+        self.base_add_size(&mut record, span);
+        self.base_add_shared_size(&mut record, span);
+        self.base_add_containers(&mut record, span);
         Ok(Value::record(record, span))
     }
 
@@ -154,9 +266,17 @@ impl CustomValue for Image {
                 vals: self
                     .repo_tags
                     .iter()
-                    .map(|repo_tag| Value::string(repo_tag, self_span.clone()))
+                    .map(|repo_tag| Value::string(repo_tag, self_span))
+                    .collect::<Vec<_>>(),
+                internal_span: self_span,
+            }),
+            "repodigests" => Ok(Value::List {
+                vals: self
+                    .repo_digests
+                    .iter()
+                    .map(|repo_digest| Value::string(repo_digest, self_span))
                     .collect::<Vec<_>>(),
-                internal_span: self_span.clone(),
+                internal_span: self_span,
             }),
             "created" => Ok(Value::Date {
                 val: self.created,
@@ -172,7 +292,7 @@ impl CustomValue for Image {
             )),
             "containers" => Ok(Value::int(self.containers, self_span)),
             _ => Err(ShellError::InvalidValue {
-                valid: "one of {id, parent_id, repo_tags, created, size, shared_size, containers}"
+                valid: "one of {id, parent_id, repotags, repodigests, created, size, shared_size, containers}"
                     .into(),
                 actual: column_name,
                 span: path_span,
@@ -188,3 +308,58 @@ impl CustomValue for Image {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_server::{Response, mock_daemon};
+
+    #[test]
+    fn split_tag_keeps_registry_ports_and_digests() {
+        assert_eq!(split_tag("rust:1.84.0"), ("rust", Some("1.84.0")));
+        assert_eq!(
+            split_tag("localhost:5000/rust"),
+            ("localhost:5000/rust", None)
+        );
+        assert_eq!(
+            split_tag("localhost:5000/rust:1.84.0"),
+            ("localhost:5000/rust", Some("1.84.0"))
+        );
+        assert_eq!(
+            split_tag("alpine@sha256:8a1f"),
+            ("alpine@sha256:8a1f", None)
+        );
+    }
+
+    #[tokio::test]
+    async fn from_reference_only_inspects_the_image() {
+        let (docker, requests) = mock_daemon(|request| {
+            if request.path.ends_with("/images/alpine:3.21/json") {
+                Response::json(
+                    200,
+                    r#"{"Id": "sha256:aded1e1a5b37", "Parent": "", "RepoTags": ["alpine:3.21"],
+                        "RepoDigests": ["alpine@sha256:a8560b36e8b8"],
+                        "Created": "2025-01-08T12:07:30.123456789Z", "Size": 7800000}"#,
+                )
+            } else {
+                Response::json(404, r#"{"message": "No such image"}"#)
+            }
+        })
+        .await;
+
+        let image = Image::from_reference(&docker, "alpine:3.21").await.unwrap();
+        assert_eq!(image.id, "sha256:aded1e1a5b37");
+        assert_eq!(image.repo_tags, vec!["alpine:3.21"]);
+        assert_eq!(image.repo_digests, vec!["alpine@sha256:a8560b36e8b8"]);
+        assert_eq!(
+            image.created.to_rfc3339(),
+            "2025-01-08T12:07:30.123456789+00:00"
+        );
+        assert_eq!(image.size, 7800000);
+        assert_eq!((image.shared_size, image.containers), (-1, -1));
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        let error = Image::from_reference(&docker, "missing").await.unwrap_err();
+        assert!(error.msg.contains("No such image"));
+    }
+}
@@ -0,0 +1,372 @@
+//! This module renders the progress streams of the daemon (pull, push, ...)
+//! to stderr.
+
+use std::io::{IsTerminal, Write};
+
+use bollard::secret::ProgressDetail;
+use nu_protocol::Filesize;
+
+/// The latest known state of a layer.
+struct LayerProgress {
+    id: String,
+    status: String,
+    current: Option<i64>,
+    total: Option<i64>,
+}
+
+impl LayerProgress {
+    fn line(&self) -> String {
+        match (self.current, self.total) {
+            (Some(current), Some(total)) if total > 0 => format!(
+                "{}: {} {}/{}",
+                self.id,
+                self.status,
+                Filesize::new(current),
+                Filesize::new(total)
+            ),
+            (Some(current), _) if current > 0 => {
+                format!("{}: {} {}", self.id, self.status, Filesize::new(current))
+            }
+            _ => format!("{}: {}", self.id, self.status),
+        }
+    }
+}
+
+/// Prints the progress of every layer on its own line.
+///
+/// On a terminal the layer lines are redrawn in place, otherwise a line is
+/// printed each time the status of a layer changes.
+pub struct ProgressPrinter {
+    layers: Vec<LayerProgress>,
+    lines_drawn: usize,
+    line_size: usize,
+    last_status: String,
+    quiet: bool,
+    terminal: bool,
+    output: Box<dyn Write + Send>,
+}
+
+impl ProgressPrinter {
+    pub fn new(quiet: bool) -> Self {
+        Self::with_output(
+            quiet,
+            std::io::stderr().is_terminal(),
+            Box::new(std::io::stderr()),
+        )
+    }
+
+    fn with_output(quiet: bool, terminal: bool, output: Box<dyn Write + Send>) -> Self {
+        Self {
+            layers: Vec::new(),
+            lines_drawn: 0,
+            line_size: 0,
+            last_status: String::new(),
+            quiet,
+            terminal,
+            output,
+        }
+    }
+
+    /// Handle one message of the progress stream.
+    /// Messages without an id are general status lines.
+    pub fn update(
+        &mut self,
+        id: Option<String>,
+        status: Option<String>,
+        progress_detail: Option<ProgressDetail>,
+    ) {
+        if self.quiet {
+            return;
+        }
+        let status = status.unwrap_or_default();
+        let (current, total) = progress_detail
+            .map(|detail| (detail.current, detail.total))
+            .unwrap_or_default();
+        match id.filter(|id| !id.is_empty()) {
+            Some(id) => self.update_layer(id, status, current, total),
+            None if !status.is_empty() => self.message(&status),
+            None => {}
+        }
+    }
+
+    /// Handle one message of a progress stream without layer ids.
+    /// Status lines are printed on their own, while the progress bar of the
+    /// current status is redrawn on a single line.
+    pub fn update_line(&mut self, status: Option<String>, progress: Option<String>) {
+        if self.quiet {
+            return;
+        }
+        let status = status.unwrap_or_default();
+        let progress = progress.unwrap_or_default();
+        if status.is_empty() && progress.is_empty() {
+            return;
+        }
+        if progress.is_empty() || !self.terminal {
+            if self.line_size > 0 {
+                let _ = writeln!(self.output);
+                self.line_size = 0;
+            }
+            if self.last_status != status {
+                let _ = writeln!(self.output, "{}", status);
+                self.last_status = status;
+            }
+            return;
+        }
+
+        let line = format!("{}: {}", status, progress);
+        if self.line_size > 0 {
+            let _ = write!(self.output, "\r{}", " ".repeat(self.line_size));
+        }
+        self.line_size = line.len();
+        let _ = write!(self.output, "\r{}", line);
+        let _ = self.output.flush();
+    }
+
+    /// Terminate the progress line left by `update_line`, if any.
+    pub fn finish(&mut self) {
+        if self.line_size > 0 {
+            let _ = writeln!(self.output);
+            self.line_size = 0;
+        }
+    }
+
+    /// Print the output of a build step as it comes. The layers of a pull
+    /// that ended are left above it instead of being redrawn below.
+    pub fn log(&mut self, output: &str) {
+        if self.quiet {
+            return;
+        }
+        self.finish();
+        self.layers.clear();
+        self.lines_drawn = 0;
+        let _ = write!(self.output, "{}", output);
+    }
+
+    fn update_layer(
+        &mut self,
+        id: String,
+        status: String,
+        current: Option<i64>,
+        total: Option<i64>,
+    ) {
+        let index = match self.layers.iter().position(|layer| layer.id == id) {
+            Some(index) => index,
+            None => {
+                self.layers.push(LayerProgress {
+                    id,
+                    status: String::new(),
+                    current: None,
+                    total: None,
+                });
+                self.layers.len() - 1
+            }
+        };
+        let layer = &mut self.layers[index];
+        let status_changed = layer.status != status;
+        layer.status = status;
+        layer.current = current;
+        layer.total = total;
+
+        if self.terminal {
+            self.redraw(None);
+        } else if status_changed {
+            let _ = writeln!(self.output, "{}: {}", layer.id, layer.status);
+        }
+    }
+
+    fn message(&mut self, message: &str) {
+        if self.terminal {
+            self.redraw(Some(message));
+        } else {
+            let _ = writeln!(self.output, "{}", message);
+        }
+    }
+
+    /// Move the cursor back over the layer lines, print `message` if any,
+    /// then print the layer lines again below it.
+    fn redraw(&mut self, message: Option<&str>) {
+        if self.lines_drawn > 0 {
+            let _ = write!(self.output, "\x1b[{}A", self.lines_drawn);
+        }
+        if let Some(message) = message {
+            let _ = writeln!(self.output, "\x1b[2K{}", message);
+        }
+        for layer in &self.layers {
+            let _ = writeln!(self.output, "\x1b[2K{}", layer.line());
+        }
+        let _ = self.output.flush();
+        self.lines_drawn = self.layers.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Collects what the printer writes.
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Output {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    fn printer(quiet: bool, terminal: bool) -> (ProgressPrinter, Output) {
+        let output = Output::default();
+        let printer = ProgressPrinter::with_output(quiet, terminal, Box::new(output.clone()));
+        (printer, output)
+    }
+
+    fn detail(current: i64, total: i64) -> Option<ProgressDetail> {
+        Some(ProgressDetail {
+            current: Some(current),
+            total: Some(total),
+        })
+    }
+
+    #[test]
+    fn layer_line_shows_the_bytes() {
+        let mut layer = LayerProgress {
+            id: "4f4fb700ef54".to_string(),
+            status: "Downloading".to_string(),
+            current: Some(512),
+            total: Some(1024),
+        };
+        assert_eq!(
+            layer.line(),
+            format!(
+                "4f4fb700ef54: Downloading {}/{}",
+                Filesize::new(512),
+                Filesize::new(1024)
+            )
+        );
+        layer.total = None;
+        assert_eq!(
+            layer.line(),
+            format!("4f4fb700ef54: Downloading {}", Filesize::new(512))
+        );
+        layer.current = None;
+        assert_eq!(layer.line(), "4f4fb700ef54: Downloading");
+    }
+
+    #[test]
+    fn prints_each_status_change_once_without_terminal() {
+        let (mut printer, output) = printer(false, false);
+        printer.update(None, Some("Pulling from library/alpine".to_string()), None);
+        for current in [100, 200, 300] {
+            printer.update(
+                Some("4f4fb700ef54".to_string()),
+                Some("Downloading".to_string()),
+                detail(current, 300),
+            );
+        }
+        printer.update(
+            Some("4f4fb700ef54".to_string()),
+            Some("Pull complete".to_string()),
+            None,
+        );
+        assert_eq!(
+            output.text(),
+            "Pulling from library/alpine\n4f4fb700ef54: Downloading\n4f4fb700ef54: Pull complete\n"
+        );
+    }
+
+    #[test]
+    fn redraws_the_layers_on_a_terminal() {
+        let (mut printer, output) = printer(false, true);
+        printer.update(Some("a".to_string()), Some("Waiting".to_string()), None);
+        printer.update(Some("b".to_string()), Some("Waiting".to_string()), None);
+        printer.update(
+            Some("a".to_string()),
+            Some("Downloading".to_string()),
+            detail(1, 2),
+        );
+        let text = output.text();
+        // The second layer moves the cursor over one line, the update of the
+        // first one over both.
+        assert!(text.contains("\x1b[1A"));
+        assert!(text.ends_with(&format!(
+            "\x1b[2A\x1b[2K{}\n\x1b[2Kb: Waiting\n",
+            LayerProgress {
+                id: "a".to_string(),
+                status: "Downloading".to_string(),
+                current: Some(1),
+                total: Some(2),
+            }
+            .line()
+        )));
+    }
+
+    #[test]
+    fn log_leaves_the_layers_above() {
+        let (mut printer, output) = printer(false, true);
+        printer.update(
+            Some("a".to_string()),
+            Some("Pull complete".to_string()),
+            None,
+        );
+        printer.log("Step 2/2 : RUN true\n");
+        printer.update(Some("b".to_string()), Some("Waiting".to_string()), None);
+        assert!(
+            output
+                .text()
+                .ends_with("Step 2/2 : RUN true\n\x1b[2Kb: Waiting\n")
+        );
+    }
+
+    #[test]
+    fn update_line_prints_each_status_once_without_terminal() {
+        let (mut printer, output) = printer(false, false);
+        printer.update_line(
+            Some("Importing".to_string()),
+            Some("[=>   ] 1MB/4MB".to_string()),
+        );
+        printer.update_line(
+            Some("Importing".to_string()),
+            Some("[===> ] 3MB/4MB".to_string()),
+        );
+        printer.update_line(Some("Loaded image: app:1.0".to_string()), None);
+        printer.finish();
+        assert_eq!(output.text(), "Importing\nLoaded image: app:1.0\n");
+    }
+
+    #[test]
+    fn update_line_redraws_the_progress_on_a_terminal() {
+        let (mut printer, output) = printer(false, true);
+        printer.update_line(Some("Importing".to_string()), Some("1MB".to_string()));
+        printer.update_line(Some("Importing".to_string()), Some("2MB".to_string()));
+        printer.finish();
+        assert_eq!(
+            output.text(),
+            format!(
+                "\rImporting: 1MB\r{}\rImporting: 2MB\n",
+                " ".repeat("Importing: 1MB".len())
+            )
+        );
+    }
+
+    #[test]
+    fn quiet_prints_nothing() {
+        let (mut printer, output) = printer(true, false);
+        printer.update(None, Some("Pulling".to_string()), None);
+        printer.update(Some("a".to_string()), Some("Waiting".to_string()), None);
+        printer.update_line(Some("Importing".to_string()), Some("1MB".to_string()));
+        printer.log("output\n");
+        printer.finish();
+        assert_eq!(output.text(), "");
+    }
+}
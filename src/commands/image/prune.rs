@@ -0,0 +1,404 @@
+//! This module is for command `ndocker image prune`.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::NdockerPlugin;
+use crate::commands::parse_filters;
+
+use nu_plugin::PluginCommand;
+use nu_protocol::{Example, Filesize, IntoPipelineData, LabeledError, Record, Span, Value};
+
+use bollard::query_parameters::{
+    ListContainersOptionsBuilder, ListImagesOptionsBuilder, PruneImagesOptionsBuilder,
+};
+use bollard::secret::ImageSummary;
+use chrono::{DateTime, Utc};
+
+pub struct ImagePruneCommand;
+
+impl ImagePruneCommand {
+    /// Parse the value of an `until` filter, which is a unix timestamp,
+    /// a RFC 3339 date or a duration like `1h30m` relative to now.
+    fn parse_until(until: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if let Ok(timestamp) = until.parse::<i64>() {
+            return DateTime::from_timestamp(timestamp, 0);
+        }
+        if let Ok(date) = DateTime::parse_from_rfc3339(until) {
+            return Some(date.to_utc());
+        }
+        let mut seconds = 0.0;
+        let mut number = String::new();
+        let mut chars = until.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c.is_ascii_digit() || c == '.' {
+                number.push(c);
+                continue;
+            }
+            let value: f64 = number.parse().ok()?;
+            number.clear();
+            seconds += match c {
+                'h' => value * 3600.0,
+                'm' if chars.peek() == Some(&'s') => {
+                    chars.next();
+                    value / 1000.0
+                }
+                'm' => value * 60.0,
+                's' => value,
+                _ => return None,
+            };
+        }
+        if !number.is_empty() {
+            return None;
+        }
+        Some(now - chrono::Duration::milliseconds((seconds * 1000.0) as i64))
+    }
+
+    /// Whether the image carries the label, `label` is either `key` or `key=value`.
+    fn has_label(image: &ImageSummary, label: &str) -> bool {
+        match label.split_once('=') {
+            Some((key, value)) => image.labels.get(key).is_some_and(|v| v == value),
+            None => image.labels.contains_key(label),
+        }
+    }
+
+    fn is_dangling(image: &ImageSummary) -> bool {
+        image
+            .repo_tags
+            .iter()
+            .all(|repo_tag| repo_tag == "<none>:<none>")
+    }
+
+    /// Compute the images the daemon would remove, without removing them.
+    /// `images` lists the intermediate images too, but the daemon only
+    /// prunes the top-level ones: the images no other image is built on.
+    fn dry_run(
+        images: Vec<ImageSummary>,
+        used_images: &HashSet<String>,
+        filters: &HashMap<String, Vec<String>>,
+        all: bool,
+    ) -> Result<Vec<ImageSummary>, LabeledError> {
+        let now = Utc::now();
+        let mut until = Vec::new();
+        for value in filters.get("until").into_iter().flatten() {
+            until.push(Self::parse_until(value, now).ok_or_else(|| {
+                LabeledError::new(format!("Invalid value for filter until: {value}"))
+            })?);
+        }
+        let labels = filters.get("label").cloned().unwrap_or_default();
+        let parents = images
+            .iter()
+            .map(|image| image.parent_id.clone())
+            .filter(|parent_id| !parent_id.is_empty())
+            .collect::<HashSet<_>>();
+
+        Ok(images
+            .into_iter()
+            .filter(|image| !parents.contains(&image.id))
+            .filter(|image| !used_images.contains(&image.id))
+            .filter(|image| all || Self::is_dangling(image))
+            .filter(|image| until.iter().all(|until| image.created < until.timestamp()))
+            .filter(|image| labels.iter().all(|label| Self::has_label(image, label)))
+            .collect())
+    }
+
+    /// The space freed by removing the image. Layers shared with other
+    /// images are kept, so they are not reclaimed.
+    fn reclaimed_size(image: &ImageSummary) -> i64 {
+        image.size - image.shared_size.max(0)
+    }
+
+    /// A row per pruned image, along with the total space reclaimed.
+    fn result_value(pruned: &[ImageSummary], space_reclaimed: i64, span: Span) -> Value {
+        let rows = pruned
+            .iter()
+            .map(|image| {
+                let mut record = Record::new();
+                record.insert("id".to_string(), Value::string(&image.id, span));
+                record.insert(
+                    "untagged".to_string(),
+                    Value::list(
+                        image
+                            .repo_tags
+                            .iter()
+                            .filter(|repo_tag| *repo_tag != "<none>:<none>")
+                            .map(|repo_tag| Value::string(repo_tag, span))
+                            .collect(),
+                        span,
+                    ),
+                );
+                record.insert(
+                    "size".to_string(),
+                    Value::filesize(Filesize::new(Self::reclaimed_size(image)), span),
+                );
+                Value::record(record, span)
+            })
+            .collect();
+        let mut result = Record::new();
+        result.insert("images".to_string(), Value::list(rows, span));
+        result.insert(
+            "space_reclaimed".to_string(),
+            Value::filesize(Filesize::new(space_reclaimed), span),
+        );
+        Value::record(result, span)
+    }
+}
+
+impl PluginCommand for ImagePruneCommand {
+    type Plugin = NdockerPlugin;
+
+    fn name(&self) -> &str {
+        "ndocker image prune"
+    }
+
+    fn description(&self) -> &str {
+        "Remove unused images."
+    }
+
+    fn extra_description(&self) -> &str {
+        "Returns a record with a row per removed image in images, with the tags it lost and the space it freed, and the total space_reclaimed."
+    }
+
+    fn signature(&self) -> nu_protocol::Signature {
+        nu_protocol::Signature::build("ndocker image prune")
+            .input_output_types(vec![(
+                nu_protocol::Type::Nothing,
+                nu_protocol::Type::record(),
+            )])
+            .switch(
+                "all",
+                "Remove all unused images, not just dangling ones",
+                Some('a'),
+            )
+            .switch(
+                "dry-run",
+                "Show the images that would be removed without removing them",
+                Some('n'),
+            )
+            .named(
+                "filter",
+                nu_protocol::Type::record().to_shape(),
+                "Only prune the images matching the filter, with keys in {until, label}",
+                Some('f'),
+            )
+    }
+
+    fn run(
+        &self,
+        plugin: &Self::Plugin,
+        _engine: &nu_plugin::EngineInterface,
+        call: &nu_plugin::EvaluatedCall,
+        _input: nu_protocol::PipelineData,
+    ) -> Result<nu_protocol::PipelineData, nu_protocol::LabeledError> {
+        let rt = tokio::runtime::Runtime::new()
+            .map_err(|e| LabeledError::new(format!("Failed to create runtime: {e}")))?;
+
+        let all = call.has_flag("all")?;
+        let mut filters = match call.get_flag_value("filter") {
+            Some(filter) => parse_filters(&filter, &["until", "label"])?,
+            None => HashMap::new(),
+        };
+        let span = call.head;
+
+        let dry_run = call.has_flag("dry-run")?;
+        let result = rt.block_on(async {
+            let images = plugin
+                .docker_socket
+                .list_images(Some(
+                    ListImagesOptionsBuilder::new()
+                        .all(true)
+                        .shared_size(true)
+                        .build(),
+                ))
+                .await
+                .map_err(|e| LabeledError::new(format!("Failed to list Docker images: {e}")))?;
+
+            if dry_run {
+                let containers = plugin
+                    .docker_socket
+                    .list_containers(Some(ListContainersOptionsBuilder::new().all(true).build()))
+                    .await
+                    .map_err(|e| {
+                        LabeledError::new(format!("Failed to list Docker containers: {e}"))
+                    })?;
+                let used_images = containers
+                    .into_iter()
+                    .filter_map(|container| container.image_id)
+                    .collect::<HashSet<_>>();
+                let pruned = Self::dry_run(images, &used_images, &filters, all)?;
+                let space_reclaimed = pruned.iter().map(Self::reclaimed_size).sum();
+                return Ok(Self::result_value(&pruned, space_reclaimed, span));
+            }
+
+            filters.insert("dangling".to_string(), vec![(!all).to_string()]);
+            let response = plugin
+                .docker_socket
+                .prune_images(Some(
+                    PruneImagesOptionsBuilder::new().filters(&filters).build(),
+                ))
+                .await
+                .map_err(|e| LabeledError::new(format!("Failed to prune Docker images: {e}")))?;
+            // The daemon also lists the deleted layers, only the images are kept.
+            let deleted = response
+                .images_deleted
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|item| item.deleted)
+                .collect::<HashSet<_>>();
+            let pruned = images
+                .into_iter()
+                .filter(|image| deleted.contains(&image.id))
+                .collect::<Vec<_>>();
+            Ok::<_, LabeledError>(Self::result_value(
+                &pruned,
+                response.space_reclaimed.unwrap_or_default(),
+                span,
+            ))
+        })?;
+
+        if let Some(timeout) = plugin.timeout {
+            rt.shutdown_timeout(timeout);
+            return Err(LabeledError::new(format!(
+                "Timeout: Operation time exceeded {} seconds",
+                timeout.as_secs()
+            )));
+        }
+
+        Ok(result.into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<nu_protocol::Example<'_>> {
+        vec![
+            Example {
+                description: "Remove the dangling images",
+                example: "ndocker image prune",
+                result: None,
+            },
+            Example {
+                description: "Show the unused images older than a day that would be removed, and the space they use",
+                example: "ndocker image prune --all --dry-run --filter {until: 24hr}",
+                result: None,
+            },
+            Example {
+                description: "Show the total space freed by removing the dangling images",
+                example: "ndocker image prune | get space_reclaimed",
+                result: None,
+            },
+            Example {
+                description: "Remove the unused images of the CI jobs",
+                example: "ndocker image prune -a -f {label: [ci=true]}",
+                result: None,
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(id: &str, repo_tags: &[&str], size: i64, shared_size: i64) -> ImageSummary {
+        ImageSummary {
+            id: id.to_string(),
+            repo_tags: repo_tags.iter().map(|tag| tag.to_string()).collect(),
+            size,
+            shared_size,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parse_until_accepts_timestamps_dates_and_durations() {
+        let now = DateTime::parse_from_rfc3339("2025-01-08T12:00:00Z")
+            .unwrap()
+            .to_utc();
+        let date = |date: &str| Some(DateTime::parse_from_rfc3339(date).unwrap().to_utc());
+        assert_eq!(
+            ImagePruneCommand::parse_until("1736337600", now),
+            date("2025-01-08T12:00:00Z")
+        );
+        assert_eq!(
+            ImagePruneCommand::parse_until("2025-01-01T00:00:00Z", now),
+            date("2025-01-01T00:00:00Z")
+        );
+        assert_eq!(
+            ImagePruneCommand::parse_until("1h30m", now),
+            date("2025-01-08T10:30:00Z")
+        );
+        assert_eq!(ImagePruneCommand::parse_until("24x", now), None);
+        assert!(ImagePruneCommand::parse_until("10s", now).is_some());
+    }
+
+    #[test]
+    fn dry_run_keeps_the_used_and_tagged_images() {
+        let images = vec![
+            image("sha256:dangling", &["<none>:<none>"], 10, 0),
+            image("sha256:used", &["<none>:<none>"], 10, 0),
+            image("sha256:tagged", &["alpine:3.21"], 10, 0),
+        ];
+        let used = HashSet::from(["sha256:used".to_string()]);
+        let ids = |pruned: Vec<ImageSummary>| {
+            pruned.into_iter().map(|image| image.id).collect::<Vec<_>>()
+        };
+        let pruned =
+            ImagePruneCommand::dry_run(images.clone(), &used, &HashMap::new(), false).unwrap();
+        assert_eq!(ids(pruned), vec!["sha256:dangling"]);
+        let pruned = ImagePruneCommand::dry_run(images, &used, &HashMap::new(), true).unwrap();
+        assert_eq!(ids(pruned), vec!["sha256:dangling", "sha256:tagged"]);
+    }
+
+    #[test]
+    fn dry_run_skips_the_intermediate_images() {
+        let mut child = image("sha256:child", &["<none>:<none>"], 30, 0);
+        child.parent_id = "sha256:parent".to_string();
+        let images = vec![image("sha256:parent", &["<none>:<none>"], 20, 0), child];
+        let pruned =
+            ImagePruneCommand::dry_run(images, &HashSet::new(), &HashMap::new(), true).unwrap();
+        let ids = pruned
+            .iter()
+            .map(|image| image.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["sha256:child"]);
+    }
+
+    #[test]
+    fn result_value_has_a_row_per_image_with_the_total() {
+        let pruned = vec![
+            image("sha256:a", &["app:1.0", "app:latest"], 300, 100),
+            image("sha256:b", &["<none>:<none>"], 50, -1),
+        ];
+        let value = ImagePruneCommand::result_value(&pruned, 250, Span::test_data());
+        let result = value.as_record().unwrap();
+        assert_eq!(
+            result.get("space_reclaimed"),
+            Some(&Value::test_filesize(250))
+        );
+        let rows = result.get("images").unwrap().as_list().unwrap();
+        assert_eq!(rows.len(), 2);
+
+        let first = rows[0].as_record().unwrap();
+        assert_eq!(first.get("id"), Some(&Value::test_string("sha256:a")));
+        assert_eq!(
+            first.get("untagged"),
+            Some(&Value::test_list(vec![
+                Value::test_string("app:1.0"),
+                Value::test_string("app:latest"),
+            ]))
+        );
+        assert_eq!(first.get("size"), Some(&Value::test_filesize(200)));
+
+        let second = rows[1].as_record().unwrap();
+        assert_eq!(second.get("untagged"), Some(&Value::test_list(vec![])));
+        assert_eq!(second.get("size"), Some(&Value::test_filesize(50)));
+    }
+
+    #[test]
+    fn result_value_has_the_total_without_images() {
+        let value = ImagePruneCommand::result_value(&[], 0, Span::test_data());
+        let result = value.as_record().unwrap();
+        assert_eq!(result.get("images"), Some(&Value::test_list(vec![])));
+        assert_eq!(
+            result.get("space_reclaimed"),
+            Some(&Value::test_filesize(0))
+        );
+    }
+}
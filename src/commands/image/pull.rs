@@ -0,0 +1,165 @@
+//! This module is for command `ndocker image pull`.
+
+use std::collections::HashMap;
+
+use crate::NdockerPlugin;
+use crate::commands::image::progress::ProgressPrinter;
+use crate::commands::image::{Image, split_tag};
+
+use nu_plugin::PluginCommand;
+use nu_protocol::{CustomValue, Example, IntoPipelineData, LabeledError, Value};
+
+use bollard::query_parameters::{CreateImageOptionsBuilder, ListImagesOptionsBuilder};
+
+use futures_util::stream::StreamExt;
+
+pub struct ImagePullCommand;
+
+impl ImagePullCommand {
+    /// Whether the reference already names a tag or a digest,
+    /// e.g. `rust:1.84.0`, `localhost:5000/rust` has no tag.
+    fn has_tag_or_digest(reference: &str) -> bool {
+        reference.contains('@') || split_tag(reference).1.is_some()
+    }
+}
+
+impl PluginCommand for ImagePullCommand {
+    type Plugin = NdockerPlugin;
+
+    fn name(&self) -> &str {
+        "ndocker image pull"
+    }
+
+    fn description(&self) -> &str {
+        "Download an image from a registry."
+    }
+
+    fn signature(&self) -> nu_protocol::Signature {
+        nu_protocol::Signature::build("ndocker image pull")
+            .input_output_types(vec![
+                (
+                    nu_protocol::Type::Nothing,
+                    nu_protocol::Type::Custom("Image".to_string().into_boxed_str()),
+                ),
+                (nu_protocol::Type::Nothing, nu_protocol::Type::table()),
+            ])
+            .switch(
+                "all-tags",
+                "Download all tagged images in the repository",
+                Some('a'),
+            )
+            .switch("quiet", "Suppress the progress output", Some('q'))
+            .named(
+                "platform",
+                nu_protocol::Type::String.to_shape(),
+                "Set the platform for the image, in the format os[/arch[/variant]], for example: linux/amd64/v5",
+                None,
+            )
+            .required(
+                "NAME[:TAG|@DIGEST]",
+                nu_protocol::Type::String.to_shape(),
+                "The name of the image to pull, the tag defaults to \"latest\".",
+            )
+    }
+
+    fn run(
+        &self,
+        plugin: &Self::Plugin,
+        _engine: &nu_plugin::EngineInterface,
+        call: &nu_plugin::EvaluatedCall,
+        _input: nu_protocol::PipelineData,
+    ) -> Result<nu_protocol::PipelineData, nu_protocol::LabeledError> {
+        let rt = tokio::runtime::Runtime::new()
+            .map_err(|e| LabeledError::new(format!("Failed to create runtime: {e}")))?;
+
+        let reference: String = call.req(0)?;
+        let all_tags = call.has_flag("all-tags")?;
+        let has_tag = Self::has_tag_or_digest(&reference);
+        if all_tags && has_tag {
+            return Err(LabeledError::new("Tag can't be used with --all-tags")
+                .with_label("Remove the tag or digest", call.positional[0].span()));
+        }
+
+        let mut options = CreateImageOptionsBuilder::new().from_image(&reference);
+        if !all_tags && !has_tag {
+            options = options.tag("latest");
+        }
+        if let Some(platform) = call.get_flag::<String>("platform")? {
+            options = options.platform(&platform);
+        }
+
+        let mut progress = ProgressPrinter::new(call.has_flag("quiet")?);
+        let result = rt.block_on(async {
+            let mut response_stream =
+                plugin
+                    .docker_socket
+                    .create_image(Some(options.build()), None, None);
+            while let Some(response) = response_stream.next().await {
+                let response = response
+                    .map_err(|e| LabeledError::new(format!("Failed to pull image: {e}")))?;
+                if let Some(error) = response.error {
+                    return Err(LabeledError::new(format!("Failed to pull image: {error}")));
+                }
+                progress.update(response.id, response.status, response.progress_detail);
+            }
+
+            if all_tags {
+                let filters = HashMap::from([("reference", vec![reference.as_str()])]);
+                let images = plugin
+                    .docker_socket
+                    .list_images(Some(
+                        ListImagesOptionsBuilder::new().filters(&filters).build(),
+                    ))
+                    .await
+                    .map_err(|e| LabeledError::new(format!("Failed to list Docker images: {e}")))?;
+                Ok(Value::list(
+                    images
+                        .into_iter()
+                        .map(Image::new)
+                        .map(|image| image.clone_value(call.head))
+                        .collect(),
+                    call.head,
+                ))
+            } else {
+                let reference = if has_tag {
+                    reference.clone()
+                } else {
+                    format!("{reference}:latest")
+                };
+                Image::from_reference(&plugin.docker_socket, &reference)
+                    .await
+                    .map(|image| image.clone_value(call.head))
+            }
+        })?;
+
+        if let Some(timeout) = plugin.timeout {
+            rt.shutdown_timeout(timeout);
+            return Err(LabeledError::new(format!(
+                "Timeout: Operation time exceeded {} seconds",
+                timeout.as_secs()
+            )));
+        }
+
+        Ok(result.into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<nu_protocol::Example<'_>> {
+        vec![
+            Example {
+                description: "Pull the latest alpine image",
+                example: "ndocker image pull alpine",
+                result: None,
+            },
+            Example {
+                description: "Pull an image by digest for a specific platform, without progress output",
+                example: "ndocker image pull -q --platform linux/arm64 alpine@sha256:8a1f59ffb675680d47db6337b49d22281a139e9d709335b492be023728e11715",
+                result: None,
+            },
+            Example {
+                description: "Pull all the tags of a repository from a local registry",
+                example: "ndocker image pull --all-tags localhost:5000/rust",
+                result: None,
+            },
+        ]
+    }
+}
@@ -0,0 +1,162 @@
+//! This module is for command `ndocker image push`.
+
+use crate::NdockerPlugin;
+use crate::commands::image::progress::ProgressPrinter;
+use crate::commands::image::split_tag;
+use crate::commands::parse_credentials;
+use crate::utils::auth::{credentials_from_config, registry_host};
+
+use nu_plugin::PluginCommand;
+use nu_protocol::{Example, Filesize, IntoPipelineData, LabeledError, Record, Value};
+
+use bollard::query_parameters::PushImageOptionsBuilder;
+
+use futures_util::stream::StreamExt;
+
+pub struct ImagePushCommand;
+
+impl ImagePushCommand {
+    /// Parse the status line sent by the daemon once a tag is pushed,
+    /// e.g. `latest: digest: sha256:7b3ccabffc97 size: 528`.
+    fn parse_pushed_tag(status: &str) -> Option<(String, String, i64)> {
+        let (tag, rest) = status.split_once(": digest: ")?;
+        let (digest, size) = rest.split_once(" size: ")?;
+        Some((
+            tag.to_string(),
+            digest.to_string(),
+            size.trim().parse().unwrap_or_default(),
+        ))
+    }
+}
+
+impl PluginCommand for ImagePushCommand {
+    type Plugin = NdockerPlugin;
+
+    fn name(&self) -> &str {
+        "ndocker image push"
+    }
+
+    fn description(&self) -> &str {
+        "Upload an image to a registry."
+    }
+
+    fn signature(&self) -> nu_protocol::Signature {
+        nu_protocol::Signature::build("ndocker image push")
+            .input_output_types(vec![(nu_protocol::Type::Nothing, nu_protocol::Type::table())])
+            .switch(
+                "all-tags",
+                "Push all tags of the image in the repository",
+                Some('a'),
+            )
+            .switch("quiet", "Suppress the progress output", Some('q'))
+            .named(
+                "auth",
+                nu_protocol::Type::record().to_shape(),
+                "Credentials of the registry, as a record {username, password, serveraddress}. Read from ~/.docker/config.json if not specified",
+                None,
+            )
+            .required(
+                "NAME[:TAG]",
+                nu_protocol::Type::String.to_shape(),
+                "The name of the image to push, the tag defaults to \"latest\".",
+            )
+    }
+
+    fn run(
+        &self,
+        plugin: &Self::Plugin,
+        _engine: &nu_plugin::EngineInterface,
+        call: &nu_plugin::EvaluatedCall,
+        _input: nu_protocol::PipelineData,
+    ) -> Result<nu_protocol::PipelineData, nu_protocol::LabeledError> {
+        let rt = tokio::runtime::Runtime::new()
+            .map_err(|e| LabeledError::new(format!("Failed to create runtime: {e}")))?;
+
+        let reference: String = call.req(0)?;
+        if reference.contains('@') {
+            return Err(LabeledError::new("Can't push an image by digest")
+                .with_label("Use a tag instead", call.positional[0].span()));
+        }
+        let (name, tag) = split_tag(&reference);
+        let all_tags = call.has_flag("all-tags")?;
+        if all_tags && tag.is_some() {
+            return Err(LabeledError::new("Tag can't be used with --all-tags")
+                .with_label("Remove the tag", call.positional[0].span()));
+        }
+
+        let mut options = PushImageOptionsBuilder::new();
+        if !all_tags {
+            options = options.tag(tag.unwrap_or("latest"));
+        }
+
+        let credentials = match call.get_flag_value("auth") {
+            Some(auth) => Some(parse_credentials(&auth)?),
+            None => credentials_from_config(&registry_host(name)).map_err(|e| {
+                LabeledError::new(format!("Failed to read registry credentials: {e}"))
+            })?,
+        };
+
+        let mut progress = ProgressPrinter::new(call.has_flag("quiet")?);
+        let mut pushed = Vec::new();
+        rt.block_on(async {
+            let mut response_stream =
+                plugin
+                    .docker_socket
+                    .push_image(name, Some(options.build()), credentials);
+            while let Some(response) = response_stream.next().await {
+                let response = response
+                    .map_err(|e| LabeledError::new(format!("Failed to push image: {e}")))?;
+                if let Some(pushed_tag) =
+                    response.status.as_deref().and_then(Self::parse_pushed_tag)
+                {
+                    pushed.push(pushed_tag);
+                }
+                progress.update_line(response.status, response.progress);
+            }
+            progress.finish();
+            Ok::<(), LabeledError>(())
+        })?;
+
+        if let Some(timeout) = plugin.timeout {
+            rt.shutdown_timeout(timeout);
+            return Err(LabeledError::new(format!(
+                "Timeout: Operation time exceeded {} seconds",
+                timeout.as_secs()
+            )));
+        }
+
+        let span = call.head;
+        let result = pushed
+            .into_iter()
+            .map(|(tag, digest, size)| {
+                let mut record = Record::new();
+                record.insert(
+                    "tag".to_string(),
+                    Value::string(format!("{name}:{tag}"), span),
+                );
+                record.insert("digest".to_string(), Value::string(digest, span));
+                record.insert(
+                    "size".to_string(),
+                    Value::filesize(Filesize::new(size), span),
+                );
+                Value::record(record, span)
+            })
+            .collect::<Vec<_>>();
+        Ok(Value::list(result, span).into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<nu_protocol::Example<'_>> {
+        vec![
+            Example {
+                description: "Push an image to a local registry",
+                example: "ndocker image push localhost:5000/rust:1.84.0",
+                result: None,
+            },
+            Example {
+                description: "Push all the tags of an image with explicit credentials",
+                example: "ndocker image push -a --auth {username: me, password: $env.REGISTRY_PASSWORD, serveraddress: registry.example.com} registry.example.com/me/app",
+                result: None,
+            },
+        ]
+    }
+}
@@ -0,0 +1,219 @@
+//! This module is for command `ndocker image rm`.
+
+use crate::NdockerPlugin;
+use crate::commands::image::references_from_input;
+
+use nu_plugin::PluginCommand;
+use nu_protocol::{Example, IntoPipelineData, LabeledError, Record, Span, Value};
+
+use bollard::query_parameters::RemoveImageOptionsBuilder;
+use bollard::secret::ImageDeleteResponseItem;
+
+pub struct ImageRmCommand;
+
+impl ImageRmCommand {
+    /// One row per image. A failure is reported in the `error` column, so
+    /// that the other images are still removed.
+    fn result_value(
+        image: &str,
+        removed: Result<Vec<ImageDeleteResponseItem>, String>,
+        span: Span,
+    ) -> Value {
+        let (removed, error) = match removed {
+            Ok(removed) => (removed, Value::nothing(span)),
+            Err(error) => (Vec::new(), Value::string(error, span)),
+        };
+        let mut record = Record::new();
+        record.insert("image".to_string(), Value::string(image, span));
+        record.insert(
+            "untagged".to_string(),
+            Value::list(
+                removed
+                    .iter()
+                    .filter_map(|item| item.untagged.as_ref())
+                    .map(|untagged| Value::string(untagged, span))
+                    .collect(),
+                span,
+            ),
+        );
+        record.insert(
+            "deleted".to_string(),
+            Value::list(
+                removed
+                    .iter()
+                    .filter_map(|item| item.deleted.as_ref())
+                    .map(|deleted| Value::string(deleted, span))
+                    .collect(),
+                span,
+            ),
+        );
+        record.insert("error".to_string(), error);
+        Value::record(record, span)
+    }
+}
+
+impl PluginCommand for ImageRmCommand {
+    type Plugin = NdockerPlugin;
+
+    fn name(&self) -> &str {
+        "ndocker image rm"
+    }
+
+    fn description(&self) -> &str {
+        "Remove one or more images."
+    }
+
+    fn extra_description(&self) -> &str {
+        "Each image gets a row. When an image cannot be removed, the reason is in the error column and the next images are still removed."
+    }
+
+    fn signature(&self) -> nu_protocol::Signature {
+        nu_protocol::Signature::build("ndocker image rm")
+            .input_output_types(vec![
+                (nu_protocol::Type::Nothing, nu_protocol::Type::table()),
+                (
+                    nu_protocol::Type::Custom("Image".to_string().into_boxed_str()),
+                    nu_protocol::Type::table(),
+                ),
+                (nu_protocol::Type::table(), nu_protocol::Type::table()),
+                (
+                    nu_protocol::Type::List(Box::new(nu_protocol::Type::Any)),
+                    nu_protocol::Type::table(),
+                ),
+                (nu_protocol::Type::String, nu_protocol::Type::table()),
+            ])
+            .switch("force", "Force removal of the image", Some('f'))
+            .switch("no-prune", "Do not delete untagged parents", None)
+            .rest(
+                "IMAGE",
+                nu_protocol::Type::String.to_shape(),
+                "The IDs or names of the images to remove, or pipe the images in.",
+            )
+    }
+
+    fn run(
+        &self,
+        plugin: &Self::Plugin,
+        _engine: &nu_plugin::EngineInterface,
+        call: &nu_plugin::EvaluatedCall,
+        input: nu_protocol::PipelineData,
+    ) -> Result<nu_protocol::PipelineData, nu_protocol::LabeledError> {
+        let rt = tokio::runtime::Runtime::new()
+            .map_err(|e| LabeledError::new(format!("Failed to create runtime: {e}")))?;
+
+        let mut images = call
+            .positional
+            .iter()
+            .map(|value| {
+                value
+                    .coerce_string()
+                    .map(|image| (image, value.span()))
+                    .map_err(|e| LabeledError::new(format!("Invalid image: {e}")))
+            })
+            .collect::<Result<Vec<(String, Span)>, LabeledError>>()?;
+        images.extend(references_from_input(input)?);
+        if images.is_empty() {
+            return Err(LabeledError::new("No image to remove")
+                .with_label("Pass IMAGE or pipe the images in", call.head));
+        }
+
+        let options = RemoveImageOptionsBuilder::new()
+            .force(call.has_flag("force")?)
+            .noprune(call.has_flag("no-prune")?)
+            .build();
+        let span = call.head;
+        let result = rt.block_on(async {
+            let mut result = Vec::new();
+            for (image, _) in images {
+                let removed = plugin
+                    .docker_socket
+                    .remove_image(&image, Some(options.clone()), None)
+                    .await
+                    .map_err(|e| format!("Failed to remove Docker image: {e}"));
+                result.push(Self::result_value(&image, removed, span));
+            }
+            result
+        });
+
+        if let Some(timeout) = plugin.timeout {
+            rt.shutdown_timeout(timeout);
+            return Err(LabeledError::new(format!(
+                "Timeout: Operation time exceeded {} seconds",
+                timeout.as_secs()
+            )));
+        }
+
+        Ok(Value::list(result, span).into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<nu_protocol::Example<'_>> {
+        vec![
+            Example {
+                description: "Remove an image by name",
+                example: "ndocker image rm rust:1.84.0",
+                result: None,
+            },
+            Example {
+                description: "Remove all the images larger than 1GB",
+                example: "ndocker images -a | where size > 1GB | ndocker image rm",
+                result: None,
+            },
+            Example {
+                description: "List the images which could not be removed",
+                example: "ndocker images | ndocker image rm | where error != null | select image error",
+                result: None,
+            },
+            Example {
+                description: "Force removal of images without deleting their untagged parents",
+                example: "ndocker image rm -f --no-prune 8daff9993116 alpine",
+                result: None,
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn result_value_lists_the_removed_layers() {
+        let span = Span::test_data();
+        let removed = vec![
+            ImageDeleteResponseItem {
+                untagged: Some("alpine:3.21".to_string()),
+                deleted: None,
+            },
+            ImageDeleteResponseItem {
+                untagged: None,
+                deleted: Some("sha256:aded1e1a5b37".to_string()),
+            },
+        ];
+        let value = ImageRmCommand::result_value("alpine:3.21", Ok(removed), span);
+        let record = value.as_record().unwrap();
+        assert_eq!(
+            record.get("untagged"),
+            Some(&Value::test_list(vec![Value::test_string("alpine:3.21")]))
+        );
+        assert_eq!(
+            record.get("deleted"),
+            Some(&Value::test_list(vec![Value::test_string(
+                "sha256:aded1e1a5b37"
+            )]))
+        );
+        assert_eq!(record.get("error"), Some(&Value::test_nothing()));
+    }
+
+    #[test]
+    fn result_value_reports_the_failure() {
+        let span = Span::test_data();
+        let value = ImageRmCommand::result_value("missing", Err("No such image".to_string()), span);
+        let record = value.as_record().unwrap();
+        assert_eq!(record.get("image"), Some(&Value::test_string("missing")));
+        assert_eq!(record.get("deleted"), Some(&Value::test_list(vec![])));
+        assert_eq!(
+            record.get("error"),
+            Some(&Value::test_string("No such image"))
+        );
+    }
+}
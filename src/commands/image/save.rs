@@ -0,0 +1,135 @@
+//! This module is for command `ndocker image save`.
+
+use std::pin::Pin;
+
+use crate::NdockerPlugin;
+use crate::commands::image::references_from_input;
+
+use bytes::Bytes;
+use nu_plugin::PluginCommand;
+use nu_protocol::{
+    ByteStream, ByteStreamType, Example, LabeledError, PipelineData, ShellError, Span,
+};
+
+use futures_util::stream::{Stream, StreamExt};
+use tokio::runtime::Runtime;
+
+type ExportStream = Pin<Box<dyn Stream<Item = Result<Bytes, bollard::errors::Error>> + Send>>;
+
+/// Pulls the chunks of the exported archive one at a time, so the archive is
+/// never held in memory as a whole.
+struct ExportIterator {
+    rt: Runtime,
+    stream: ExportStream,
+    span: Span,
+}
+
+impl Iterator for ExportIterator {
+    type Item = Result<Vec<u8>, ShellError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chunk = self.rt.block_on(self.stream.next())?;
+        Some(
+            chunk
+                .map(|chunk| chunk.to_vec())
+                .map_err(|e| ShellError::GenericError {
+                    error: "Failed to save Docker image".to_string(),
+                    msg: e.to_string(),
+                    span: Some(self.span),
+                    help: None,
+                    inner: vec![],
+                }),
+        )
+    }
+}
+
+pub struct ImageSaveCommand;
+
+impl PluginCommand for ImageSaveCommand {
+    type Plugin = NdockerPlugin;
+
+    fn name(&self) -> &str {
+        "ndocker image save"
+    }
+
+    fn description(&self) -> &str {
+        "Save one or more images to a tar archive, streamed as binary."
+    }
+
+    fn signature(&self) -> nu_protocol::Signature {
+        nu_protocol::Signature::build("ndocker image save")
+            .input_output_types(vec![
+                (nu_protocol::Type::Nothing, nu_protocol::Type::Binary),
+                (
+                    nu_protocol::Type::Custom("Image".to_string().into_boxed_str()),
+                    nu_protocol::Type::Binary,
+                ),
+                (nu_protocol::Type::table(), nu_protocol::Type::Binary),
+                (
+                    nu_protocol::Type::List(Box::new(nu_protocol::Type::Any)),
+                    nu_protocol::Type::Binary,
+                ),
+                (nu_protocol::Type::String, nu_protocol::Type::Binary),
+            ])
+            .rest(
+                "IMAGE",
+                nu_protocol::Type::String.to_shape(),
+                "The IDs or names of the images to save, or pipe the images in.",
+            )
+    }
+
+    fn run(
+        &self,
+        plugin: &Self::Plugin,
+        engine: &nu_plugin::EngineInterface,
+        call: &nu_plugin::EvaluatedCall,
+        input: nu_protocol::PipelineData,
+    ) -> Result<nu_protocol::PipelineData, nu_protocol::LabeledError> {
+        let rt = Runtime::new()
+            .map_err(|e| LabeledError::new(format!("Failed to create runtime: {e}")))?;
+
+        let mut images = call.rest::<String>(0)?;
+        images.extend(
+            references_from_input(input)?
+                .into_iter()
+                .map(|(image, _)| image),
+        );
+        if images.is_empty() {
+            return Err(LabeledError::new("No image to save")
+                .with_label("Pass IMAGE or pipe the images in", call.head));
+        }
+
+        let images = images.iter().map(String::as_str).collect::<Vec<_>>();
+        let stream: ExportStream = Box::pin(plugin.docker_socket.export_images(&images));
+        let iter = ExportIterator {
+            rt,
+            stream,
+            span: call.head,
+        };
+
+        Ok(PipelineData::ByteStream(
+            ByteStream::from_result_iter(
+                iter,
+                call.head,
+                engine.signals().clone(),
+                ByteStreamType::Binary,
+            ),
+            None,
+        ))
+    }
+
+    fn examples(&self) -> Vec<nu_protocol::Example<'_>> {
+        vec![
+            Example {
+                description: "Save an image to a tar archive",
+                example: "ndocker image save rust:1.84.0 | save rust.tar",
+                result: None,
+            },
+            Example {
+                description: "Save all the images of a repository to a compressed archive",
+                example: "ndocker images | where {|i| $i.repotags | any {str starts-with 'rust:'}} | ndocker image save | ^gzip | save rust.tar.gz",
+                result: None,
+            },
+        ]
+    }
+}
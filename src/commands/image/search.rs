@@ -0,0 +1,240 @@
+//! This module is for command `ndocker image search`.
+
+use crate::NdockerPlugin;
+use crate::commands::parse_filters;
+
+use nu_plugin::PluginCommand;
+use nu_protocol::{Example, IntoPipelineData, LabeledError, Record, Span, Value};
+
+use bollard::query_parameters::SearchImagesOptionsBuilder;
+use bollard::secret::ImageSearchResponseItem;
+
+/// Filters supported by the daemon when searching images.
+pub const SEARCH_FILTERS: &[&str] = &["is-official", "stars"];
+
+/// The most results the registry returns for a search.
+const MAX_LIMIT: i64 = 100;
+
+pub struct ImageSearchCommand;
+
+impl ImageSearchCommand {
+    fn result_value(item: ImageSearchResponseItem, span: Span) -> Value {
+        let mut base = Record::new();
+        base.insert(
+            "name".to_string(),
+            Value::string(item.name.unwrap_or_default(), span),
+        );
+        base.insert(
+            "description".to_string(),
+            Value::string(item.description.unwrap_or_default(), span),
+        );
+        base.insert(
+            "stars".to_string(),
+            Value::int(item.star_count.unwrap_or_default(), span),
+        );
+        base.insert(
+            "official".to_string(),
+            Value::bool(item.is_official.unwrap_or_default(), span),
+        );
+        base.insert(
+            "automated".to_string(),
+            Value::bool(item.is_automated.unwrap_or_default(), span),
+        );
+        Value::record(base, span)
+    }
+}
+
+impl PluginCommand for ImageSearchCommand {
+    type Plugin = NdockerPlugin;
+
+    fn name(&self) -> &str {
+        "ndocker image search"
+    }
+
+    fn description(&self) -> &str {
+        "Search a registry for Docker images."
+    }
+
+    fn signature(&self) -> nu_protocol::Signature {
+        nu_protocol::Signature::build("ndocker image search")
+            .input_output_types(vec![(
+                nu_protocol::Type::Nothing,
+                nu_protocol::Type::table(),
+            )])
+            .named(
+                "limit",
+                nu_protocol::Type::Int.to_shape(),
+                "The maximum number of results, up to 100",
+                Some('l'),
+            )
+            .named(
+                "filter",
+                nu_protocol::Type::record().to_shape(),
+                "Filter the results, with keys in {is-official, stars}",
+                Some('f'),
+            )
+            .required(
+                "TERM",
+                nu_protocol::Type::String.to_shape(),
+                "The term to search for, prefixed with the registry to search another one than Docker Hub.",
+            )
+    }
+
+    fn run(
+        &self,
+        plugin: &Self::Plugin,
+        _engine: &nu_plugin::EngineInterface,
+        call: &nu_plugin::EvaluatedCall,
+        _input: nu_protocol::PipelineData,
+    ) -> Result<nu_protocol::PipelineData, nu_protocol::LabeledError> {
+        let rt = tokio::runtime::Runtime::new()
+            .map_err(|e| LabeledError::new(format!("Failed to create runtime: {e}")))?;
+
+        let term: String = call.req(0)?;
+        let mut options = SearchImagesOptionsBuilder::new().term(&term);
+        if let Some(limit) = call.get_flag::<i64>("limit")? {
+            if !(1..=MAX_LIMIT).contains(&limit) {
+                return Err(LabeledError::new("Invalid --limit")
+                    .with_label(format!("Expected between 1 and {MAX_LIMIT}"), call.head));
+            }
+            options = options.limit(limit as i32);
+        }
+        if let Some(filter) = call.get_flag_value("filter") {
+            options = options.filters(&parse_filters(&filter, SEARCH_FILTERS)?);
+        }
+
+        let results = rt
+            .block_on(plugin.docker_socket.search_images(options.build()))
+            .map_err(|e| {
+                LabeledError::new("Failed to search images")
+                    .with_label(e.to_string(), call.positional[0].span())
+            })?;
+
+        if let Some(timeout) = plugin.timeout {
+            rt.shutdown_timeout(timeout);
+            return Err(LabeledError::new(format!(
+                "Timeout: Operation time exceeded {} seconds",
+                timeout.as_secs()
+            )));
+        }
+
+        let span = call.head;
+        let result = results
+            .into_iter()
+            .map(|item| Self::result_value(item, span))
+            .collect();
+        Ok(Value::list(result, span).into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<nu_protocol::Example<'_>> {
+        vec![
+            Example {
+                description: "Search Docker Hub for the official nginx image",
+                example: "ndocker image search nginx --filter {is-official: true}",
+                result: None,
+            },
+            Example {
+                description: "Show the 5 most starred postgres images with at least 50 stars",
+                example: "ndocker image search postgres --limit 25 --filter {stars: 50} | sort-by stars --reverse | first 5",
+                result: None,
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_server::{Response, mock_daemon};
+    use nu_protocol::record;
+
+    #[test]
+    fn result_value_reads_the_registry_fields() {
+        let item = ImageSearchResponseItem {
+            name: Some("nginx".to_string()),
+            description: Some("Official build of Nginx.".to_string()),
+            star_count: Some(20734),
+            is_official: Some(true),
+            is_automated: Some(false),
+        };
+        assert_eq!(
+            ImageSearchCommand::result_value(item, Span::test_data()),
+            Value::test_record(record! {
+                "name" => Value::test_string("nginx"),
+                "description" => Value::test_string("Official build of Nginx."),
+                "stars" => Value::test_int(20734),
+                "official" => Value::test_bool(true),
+                "automated" => Value::test_bool(false),
+            })
+        );
+
+        // Registries other than Docker Hub leave fields out.
+        let value =
+            ImageSearchCommand::result_value(ImageSearchResponseItem::default(), Span::test_data());
+        let record = value.as_record().unwrap();
+        assert_eq!(record.get("description"), Some(&Value::test_string("")));
+        assert_eq!(record.get("stars"), Some(&Value::test_int(0)));
+        assert_eq!(record.get("official"), Some(&Value::test_bool(false)));
+    }
+
+    #[test]
+    fn filters_are_limited_to_the_search_filters() {
+        let filters = parse_filters(
+            &Value::test_record(record! {
+                "is-official" => Value::test_bool(true),
+                "stars" => Value::test_int(50),
+            }),
+            SEARCH_FILTERS,
+        )
+        .unwrap();
+        assert_eq!(filters["is-official"], vec!["true"]);
+        assert_eq!(filters["stars"], vec!["50"]);
+
+        for invalid in [
+            Value::test_record(record! { "dangling" => Value::test_bool(true) }),
+            Value::test_record(record! { "stars" => Value::test_float(4.5) }),
+            Value::test_string("stars=50"),
+        ] {
+            assert!(
+                parse_filters(&invalid, SEARCH_FILTERS).is_err(),
+                "{invalid:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn search_sends_the_term_limit_and_filters() {
+        let (docker, requests) = mock_daemon(|_| {
+            Response::json(
+                200,
+                r#"[{"name": "postgres", "description": "", "star_count": 14000,
+                     "is_official": true, "is_automated": false}]"#,
+            )
+        })
+        .await;
+
+        let filters = parse_filters(
+            &Value::test_record(record! { "stars" => Value::test_int(50) }),
+            SEARCH_FILTERS,
+        )
+        .unwrap();
+        let options = SearchImagesOptionsBuilder::new()
+            .term("postgres")
+            .limit(25)
+            .filters(&filters)
+            .build();
+        let results = docker.search_images(options).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].star_count, Some(14000));
+
+        let requests = requests.lock().unwrap();
+        let path = &requests[0].path;
+        assert!(path.contains("/images/search?"), "{path}");
+        assert!(path.contains("term=postgres"), "{path}");
+        assert!(path.contains("limit=25"), "{path}");
+        assert!(
+            path.contains("filters=%7B%22stars%22%3A%5B%2250%22%5D%7D"),
+            "{path}"
+        );
+    }
+}
@@ -0,0 +1,121 @@
+//! This module is for command `ndocker image tag`.
+
+use crate::NdockerPlugin;
+use crate::commands::image::{Image, references_from_input, split_tag};
+
+use nu_plugin::PluginCommand;
+use nu_protocol::{CustomValue, Example, IntoPipelineData, LabeledError};
+
+use bollard::query_parameters::TagImageOptionsBuilder;
+
+pub struct ImageTagCommand;
+
+impl PluginCommand for ImageTagCommand {
+    type Plugin = NdockerPlugin;
+
+    fn name(&self) -> &str {
+        "ndocker image tag"
+    }
+
+    fn description(&self) -> &str {
+        "Create a tag TARGET_IMAGE that refers to SOURCE_IMAGE."
+    }
+
+    fn signature(&self) -> nu_protocol::Signature {
+        nu_protocol::Signature::build("ndocker image tag")
+            .input_output_types(vec![
+                (
+                    nu_protocol::Type::Nothing,
+                    nu_protocol::Type::Custom("Image".to_string().into_boxed_str()),
+                ),
+                (
+                    nu_protocol::Type::Custom("Image".to_string().into_boxed_str()),
+                    nu_protocol::Type::Custom("Image".to_string().into_boxed_str()),
+                ),
+                (
+                    nu_protocol::Type::String,
+                    nu_protocol::Type::Custom("Image".to_string().into_boxed_str()),
+                ),
+            ])
+            .required(
+                "SOURCE_IMAGE[:TAG]",
+                nu_protocol::Type::String.to_shape(),
+                "The image to tag. If the image is piped in, this is the target instead.",
+            )
+            .optional(
+                "TARGET_IMAGE[:TAG]",
+                nu_protocol::Type::String.to_shape(),
+                "The new tag of the image, the tag defaults to \"latest\".",
+            )
+    }
+
+    fn run(
+        &self,
+        plugin: &Self::Plugin,
+        _engine: &nu_plugin::EngineInterface,
+        call: &nu_plugin::EvaluatedCall,
+        input: nu_protocol::PipelineData,
+    ) -> Result<nu_protocol::PipelineData, nu_protocol::LabeledError> {
+        let rt = tokio::runtime::Runtime::new()
+            .map_err(|e| LabeledError::new(format!("Failed to create runtime: {e}")))?;
+
+        let first: String = call.req(0)?;
+        let (source, target) = match call.opt::<String>(1)? {
+            Some(target) => (first, target),
+            None => {
+                let mut sources = references_from_input(input)?;
+                if sources.len() != 1 {
+                    return Err(
+                        LabeledError::new("Expected exactly one source image").with_label(
+                            format!(
+                                "Pipe in one image or pass SOURCE_IMAGE, got {} images",
+                                sources.len()
+                            ),
+                            call.head,
+                        ),
+                    );
+                }
+                (sources.remove(0).0, first)
+            }
+        };
+
+        let (repo, tag) = split_tag(&target);
+        let options = TagImageOptionsBuilder::new()
+            .repo(repo)
+            .tag(tag.unwrap_or("latest"))
+            .build();
+        let image = rt.block_on(async {
+            plugin
+                .docker_socket
+                .tag_image(&source, Some(options))
+                .await
+                .map_err(|e| LabeledError::new(format!("Failed to tag Docker image: {e}")))?;
+            Image::from_reference(&plugin.docker_socket, &source).await
+        })?;
+
+        if let Some(timeout) = plugin.timeout {
+            rt.shutdown_timeout(timeout);
+            return Err(LabeledError::new(format!(
+                "Timeout: Operation time exceeded {} seconds",
+                timeout.as_secs()
+            )));
+        }
+
+        Ok(image.clone_value(call.head).into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<nu_protocol::Example<'_>> {
+        vec![
+            Example {
+                description: "Tag an image for a local registry",
+                example: "ndocker image tag rust:1.84.0 localhost:5000/rust:1.84.0",
+                result: None,
+            },
+            Example {
+                description: "Tag the largest image",
+                example: "ndocker images -a | sort-by size | last | ndocker image tag biggest",
+                result: None,
+            },
+        ]
+    }
+}
@@ -0,0 +1,73 @@
+//! Helpers for the interactive modes of the commands.
+//! The questions are asked through the `input` command of the engine.
+
+use nu_plugin::{EngineInterface, EvaluatedCall};
+use nu_protocol::{DeclId, LabeledError, PipelineData, Span, Value};
+
+pub struct Prompt<'a> {
+    engine: &'a EngineInterface,
+    input_decl: DeclId,
+    span: Span,
+}
+
+impl<'a> Prompt<'a> {
+    pub fn new(engine: &'a EngineInterface, span: Span) -> Result<Self, LabeledError> {
+        let input_decl = engine
+            .find_decl("input")?
+            .ok_or_else(|| LabeledError::new("Failed to find 'input' operation".to_string()))?;
+        Ok(Self {
+            engine,
+            input_decl,
+            span,
+        })
+    }
+
+    /// Ask a question and return the trimmed answer.
+    pub fn ask(&self, question: &str) -> Result<String, LabeledError> {
+        let answer = self.engine.call_decl(
+            self.input_decl,
+            EvaluatedCall::new(self.span).with_positional(Value::string(question, self.span)),
+            PipelineData::Empty,
+            true,
+            false,
+        )?;
+        Ok(answer
+            .into_value(self.span)?
+            .coerce_into_string()?
+            .trim()
+            .to_string())
+    }
+
+    /// Ask a question until the answer passes `validate`.
+    /// An empty answer is returned as is, to let the user skip the question.
+    pub fn ask_valid(
+        &self,
+        question: &str,
+        validate: impl Fn(&str) -> Result<(), String>,
+    ) -> Result<String, LabeledError> {
+        loop {
+            let answer = self.ask(question)?;
+            if answer.is_empty() {
+                return Ok(answer);
+            }
+            match validate(&answer) {
+                Ok(()) => return Ok(answer),
+                Err(e) => eprintln!("Invalid value: {e}"),
+            }
+        }
+    }
+
+    /// Ask a yes/no question, `default` is used for an empty answer.
+    pub fn confirm(&self, question: &str, default: bool) -> Result<bool, LabeledError> {
+        let hint = if default { "[Y/n]" } else { "[y/N]" };
+        loop {
+            let answer = self.ask(&format!("{question} {hint} "))?;
+            match answer.to_lowercase().as_str() {
+                "" => return Ok(default),
+                "y" | "yes" => return Ok(true),
+                "n" | "no" => return Ok(false),
+                _ => eprintln!("Please answer y or n."),
+            }
+        }
+    }
+}
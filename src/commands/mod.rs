@@ -1,11 +1,19 @@
+pub mod container;
 pub mod image;
+pub mod interactive;
+pub mod registry;
+
+use std::collections::HashMap;
+
+use bollard::auth::DockerCredentials;
+use nu_protocol::{LabeledError, Value};
 
 pub fn shorten_id(id: &str) -> String {
     if id.is_empty() {
         return String::new();
     }
     id.split(':')
-        .last()
+        .next_back()
         .unwrap_or(id)
         .chars()
         .take(12)
@@ -18,3 +26,98 @@ pub fn shorten_string(s: &str, max_length: usize) -> String {
     }
     format!("{}...", &s[..max_length - 3])
 }
+
+/// Translate a NuShell record like `{dangling: true, label: [a=b]}` into the
+/// `map[string][]string` filters accepted by the Docker daemon.
+///
+/// Only the keys in `valid_keys` are accepted. Each value can be a string, a
+/// bool, an int, a date, a duration, a record (rendered as `key=value` pairs)
+/// or a list of those.
+pub fn parse_filters(
+    filters: &Value,
+    valid_keys: &[&str],
+) -> Result<HashMap<String, Vec<String>>, LabeledError> {
+    let record = filters.as_record().map_err(|_| {
+        LabeledError::new("Invalid filter").with_label("Expected a record", filters.span())
+    })?;
+    let mut result = HashMap::new();
+    for (key, value) in record.iter() {
+        if !valid_keys.contains(&key.as_str()) {
+            return Err(
+                LabeledError::new(format!("Unknown filter: {key}")).with_label(
+                    format!("Expected one of {{{}}}", valid_keys.join(", ")),
+                    value.span(),
+                ),
+            );
+        }
+        let mut values = Vec::new();
+        match value {
+            Value::List { vals, .. } => {
+                for val in vals {
+                    values.extend(filter_value_to_strings(key, val)?);
+                }
+            }
+            _ => values.extend(filter_value_to_strings(key, value)?),
+        }
+        result.insert(key.clone(), values);
+    }
+    Ok(result)
+}
+
+fn filter_value_to_strings(key: &str, value: &Value) -> Result<Vec<String>, LabeledError> {
+    match value {
+        Value::String { val, .. } => Ok(vec![val.clone()]),
+        Value::Bool { val, .. } => Ok(vec![val.to_string()]),
+        Value::Int { val, .. } => Ok(vec![val.to_string()]),
+        Value::Date { val, .. } => Ok(vec![val.timestamp().to_string()]),
+        Value::Duration { val, .. } => Ok(vec![format!("{}s", val / 1_000_000_000)]),
+        Value::Record { val, .. } => val
+            .iter()
+            .map(|(k, v)| {
+                v.coerce_str()
+                    .map(|v| format!("{k}={v}"))
+                    .map_err(|_| invalid_filter_value(key, v))
+            })
+            .collect(),
+        _ => Err(invalid_filter_value(key, value)),
+    }
+}
+
+fn invalid_filter_value(key: &str, value: &Value) -> LabeledError {
+    LabeledError::new(format!("Invalid value for filter: {key}")).with_label(
+        format!("Unsupported type: {}", value.get_type()),
+        value.span(),
+    )
+}
+
+/// Translate a NuShell record like `{username, password, serveraddress}`
+/// into the credentials used to access a registry.
+pub fn parse_credentials(auth: &Value) -> Result<DockerCredentials, LabeledError> {
+    let record = auth.as_record().map_err(|_| {
+        LabeledError::new("Invalid credentials").with_label("Expected a record", auth.span())
+    })?;
+    let mut credentials = DockerCredentials::default();
+    for (key, value) in record.iter() {
+        let field = match key.as_str() {
+            "username" => &mut credentials.username,
+            "password" => &mut credentials.password,
+            "auth" => &mut credentials.auth,
+            "email" => &mut credentials.email,
+            "serveraddress" => &mut credentials.serveraddress,
+            "identitytoken" => &mut credentials.identitytoken,
+            "registrytoken" => &mut credentials.registrytoken,
+            _ => {
+                return Err(LabeledError::new(format!("Unknown credential field: {key}"))
+                    .with_label(
+                        "Expected one of {username, password, auth, email, serveraddress, identitytoken, registrytoken}",
+                        value.span(),
+                    ));
+            }
+        };
+        *field = Some(value.coerce_string().map_err(|_| {
+            LabeledError::new(format!("Invalid value for credential field: {key}"))
+                .with_label("Expected a string", value.span())
+        })?);
+    }
+    Ok(credentials)
+}
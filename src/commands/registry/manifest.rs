@@ -0,0 +1,283 @@
+//! This module is for command `ndocker registry manifest`.
+
+use crate::NdockerPlugin;
+use crate::commands::registry::registry_client;
+use crate::utils::registry::{Descriptor, Manifest, RegistryClient, RegistryError};
+
+use nu_plugin::PluginCommand;
+use nu_protocol::{Example, Filesize, IntoPipelineData, LabeledError, Record, Span, Value};
+
+use futures_util::stream::{self, StreamExt, TryStreamExt};
+
+/// Number of platform manifests fetched at the same time.
+const MANIFEST_CONCURRENCY: usize = 8;
+
+pub struct RegistryManifestCommand;
+
+impl RegistryManifestCommand {
+    /// Fetch the manifest of each platform of an index, to know the size of
+    /// its image.
+    async fn platform_manifests(
+        client: &RegistryClient,
+        index: &Manifest,
+    ) -> Result<Vec<Manifest>, RegistryError> {
+        stream::iter(&index.manifests)
+            .map(|descriptor| client.manifest(&descriptor.digest))
+            .buffered(MANIFEST_CONCURRENCY)
+            .try_collect()
+            .await
+    }
+
+    fn layer_value(layer: &Descriptor, span: Span) -> Value {
+        let mut base = Record::new();
+        base.insert("digest".to_string(), Value::string(&layer.digest, span));
+        base.insert(
+            "media_type".to_string(),
+            Value::string(&layer.media_type, span),
+        );
+        base.insert(
+            "size".to_string(),
+            Value::filesize(Filesize::new(layer.size), span),
+        );
+        Value::record(base, span)
+    }
+
+    fn platform_value(descriptor: &Descriptor, manifest: &Manifest, span: Span) -> Value {
+        let platform = descriptor.platform.clone().unwrap_or_default();
+        let mut base = Record::new();
+        base.insert(
+            "platform".to_string(),
+            Value::string(platform.to_string(), span),
+        );
+        base.insert("os".to_string(), Value::string(&platform.os, span));
+        base.insert(
+            "architecture".to_string(),
+            Value::string(&platform.architecture, span),
+        );
+        base.insert(
+            "variant".to_string(),
+            match &platform.variant {
+                Some(variant) => Value::string(variant, span),
+                None => Value::nothing(span),
+            },
+        );
+        base.insert(
+            "digest".to_string(),
+            Value::string(&descriptor.digest, span),
+        );
+        base.insert(
+            "media_type".to_string(),
+            Value::string(&descriptor.media_type, span),
+        );
+        base.insert(
+            "size".to_string(),
+            Value::filesize(Filesize::new(manifest.image_size()), span),
+        );
+        Value::record(base, span)
+    }
+}
+
+impl PluginCommand for RegistryManifestCommand {
+    type Plugin = NdockerPlugin;
+
+    fn name(&self) -> &str {
+        "ndocker registry manifest"
+    }
+
+    fn description(&self) -> &str {
+        "Show the manifest of an image, asking the registry directly."
+    }
+
+    fn extra_description(&self) -> &str {
+        "For a multi-platform image, the digest and the compressed size of the image of each platform are listed. For a single image, its config and its layers are."
+    }
+
+    fn signature(&self) -> nu_protocol::Signature {
+        nu_protocol::Signature::build("ndocker registry manifest")
+            .input_output_types(vec![(
+                nu_protocol::Type::Nothing,
+                nu_protocol::Type::record(),
+            )])
+            .required(
+                "REF",
+                nu_protocol::Type::String.to_shape(),
+                "The image, with a tag or a digest, `latest` by default.",
+            )
+    }
+
+    fn run(
+        &self,
+        plugin: &Self::Plugin,
+        _engine: &nu_plugin::EngineInterface,
+        call: &nu_plugin::EvaluatedCall,
+        _input: nu_protocol::PipelineData,
+    ) -> Result<nu_protocol::PipelineData, nu_protocol::LabeledError> {
+        let rt = tokio::runtime::Runtime::new()
+            .map_err(|e| LabeledError::new(format!("Failed to create runtime: {e}")))?;
+
+        let name: String = call.req(0)?;
+        let name_span = call.positional[0].span();
+        let (mut reference, client) = registry_client(&name, name_span)?;
+        let tag = reference
+            .reference
+            .get_or_insert_with(|| "latest".to_string())
+            .clone();
+
+        let (manifest, platforms) = rt
+            .block_on(async {
+                let manifest = client.manifest(&tag).await?;
+                let platforms = if manifest.is_index() {
+                    Self::platform_manifests(&client, &manifest).await?
+                } else {
+                    Vec::new()
+                };
+                Ok::<_, RegistryError>((manifest, platforms))
+            })
+            .map_err(|e| {
+                LabeledError::new("Failed to get the manifest").with_label(e.to_string(), name_span)
+            })?;
+
+        if let Some(timeout) = plugin.timeout {
+            rt.shutdown_timeout(timeout);
+            return Err(LabeledError::new(format!(
+                "Timeout: Operation time exceeded {} seconds",
+                timeout.as_secs()
+            )));
+        }
+
+        let span = call.head;
+        let mut base = Record::new();
+        base.insert(
+            "name".to_string(),
+            Value::string(reference.to_string(), span),
+        );
+        base.insert("digest".to_string(), Value::string(&manifest.digest, span));
+        base.insert(
+            "media_type".to_string(),
+            Value::string(&manifest.media_type, span),
+        );
+        if manifest.is_index() {
+            let platforms = manifest
+                .manifests
+                .iter()
+                .zip(&platforms)
+                .map(|(descriptor, platform)| Self::platform_value(descriptor, platform, span))
+                .collect();
+            base.insert("platforms".to_string(), Value::list(platforms, span));
+        } else {
+            base.insert(
+                "config".to_string(),
+                match &manifest.config {
+                    Some(config) => Self::layer_value(config, span),
+                    None => Value::nothing(span),
+                },
+            );
+            let layers = manifest
+                .layers
+                .iter()
+                .map(|layer| Self::layer_value(layer, span))
+                .collect();
+            base.insert("layers".to_string(), Value::list(layers, span));
+            base.insert(
+                "size".to_string(),
+                Value::filesize(Filesize::new(manifest.image_size()), span),
+            );
+        }
+        Ok(Value::record(base, span).into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<nu_protocol::Example<'_>> {
+        vec![
+            Example {
+                description: "List the platforms of the official alpine image with their sizes",
+                example: "ndocker registry manifest alpine:3.21 | get platforms | select platform digest size",
+                result: None,
+            },
+            Example {
+                description: "Show the layers of the linux/arm64 image of alpine",
+                example: "let index = ndocker registry manifest alpine:3.21; ndocker registry manifest $\"alpine@($index.platforms | where platform == linux/arm64/v8 | first | get digest)\" | get layers",
+                result: None,
+            },
+            Example {
+                description: "Show the manifest of an image of a local registry",
+                example: "ndocker registry manifest localhost:5000/app:1.0",
+                result: None,
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::registry::{DOCKER_MANIFEST, OCI_INDEX, RegistryReference};
+    use crate::utils::test_server::{Response, serve_tcp};
+
+    #[tokio::test]
+    async fn platform_manifests_size_each_platform_of_an_index() {
+        let (address, requests) = serve_tcp(|request| {
+            let (digest, size) = match request.path.as_str() {
+                "/v2/app/manifests/latest" => {
+                    return Response::json(
+                        200,
+                        r#"{"manifests": [
+                            {"mediaType": "application/vnd.docker.distribution.manifest.v2+json", "digest": "sha256:amd64", "size": 528,
+                             "platform": {"architecture": "amd64", "os": "linux"}},
+                            {"mediaType": "application/vnd.docker.distribution.manifest.v2+json", "digest": "sha256:arm", "size": 528,
+                             "platform": {"architecture": "arm", "os": "linux", "variant": "v7"}}]}"#,
+                    )
+                    .header("Content-Type", OCI_INDEX);
+                }
+                "/v2/app/manifests/sha256:amd64" => ("sha256:l-amd64", 3000),
+                _ => ("sha256:l-arm", 2000),
+            };
+            Response::json(
+                200,
+                &format!(
+                    r#"{{"config": {{"digest": "sha256:c", "size": 100}},
+                        "layers": [{{"digest": "{digest}", "size": {size}}}]}}"#
+                ),
+            )
+            .header("Content-Type", DOCKER_MANIFEST)
+        })
+        .await;
+        let reference = RegistryReference::parse(&format!("{}/app", address)).unwrap();
+        let client = RegistryClient::new(&reference).unwrap();
+
+        let index = client.manifest("latest").await.unwrap();
+        let platforms = RegistryManifestCommand::platform_manifests(&client, &index)
+            .await
+            .unwrap();
+        assert_eq!(requests.lock().unwrap().len(), 3);
+
+        let values = index
+            .manifests
+            .iter()
+            .zip(&platforms)
+            .map(|(descriptor, manifest)| {
+                RegistryManifestCommand::platform_value(descriptor, manifest, Span::test_data())
+            })
+            .collect::<Vec<_>>();
+        let columns = |column: &str| {
+            values
+                .iter()
+                .map(|value| value.as_record().unwrap().get(column).unwrap().clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            columns("platform"),
+            vec![
+                Value::test_string("linux/amd64"),
+                Value::test_string("linux/arm/v7")
+            ]
+        );
+        assert_eq!(
+            columns("variant"),
+            vec![Value::test_nothing(), Value::test_string("v7")]
+        );
+        assert_eq!(
+            columns("size"),
+            vec![Value::test_filesize(3100), Value::test_filesize(2100)]
+        );
+    }
+}
@@ -0,0 +1,19 @@
+pub mod manifest;
+pub mod tags;
+
+use crate::utils::registry::{RegistryClient, RegistryReference};
+
+use nu_protocol::{LabeledError, Span};
+
+/// Parse an image reference and create a client for its repository.
+pub fn registry_client(
+    name: &str,
+    span: Span,
+) -> Result<(RegistryReference, RegistryClient), LabeledError> {
+    let reference = RegistryReference::parse(name)
+        .map_err(|e| LabeledError::new("Invalid reference").with_label(e.to_string(), span))?;
+    let client = RegistryClient::new(&reference).map_err(|e| {
+        LabeledError::new("Failed to create the registry client").with_label(e.to_string(), span)
+    })?;
+    Ok((reference, client))
+}
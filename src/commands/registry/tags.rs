@@ -0,0 +1,87 @@
+//! This module is for command `ndocker registry tags`.
+
+use crate::NdockerPlugin;
+use crate::commands::registry::registry_client;
+
+use nu_plugin::PluginCommand;
+use nu_protocol::{Example, IntoPipelineData, LabeledError, Value};
+
+pub struct RegistryTagsCommand;
+
+impl PluginCommand for RegistryTagsCommand {
+    type Plugin = NdockerPlugin;
+
+    fn name(&self) -> &str {
+        "ndocker registry tags"
+    }
+
+    fn description(&self) -> &str {
+        "List the tags of a repository, asking the registry directly."
+    }
+
+    fn signature(&self) -> nu_protocol::Signature {
+        nu_protocol::Signature::build("ndocker registry tags")
+            .input_output_types(vec![(
+                nu_protocol::Type::Nothing,
+                nu_protocol::Type::List(Box::new(nu_protocol::Type::String)),
+            )])
+            .required(
+                "REPO",
+                nu_protocol::Type::String.to_shape(),
+                "The repository, e.g. alpine or localhost:5000/app.",
+            )
+    }
+
+    fn run(
+        &self,
+        plugin: &Self::Plugin,
+        _engine: &nu_plugin::EngineInterface,
+        call: &nu_plugin::EvaluatedCall,
+        _input: nu_protocol::PipelineData,
+    ) -> Result<nu_protocol::PipelineData, nu_protocol::LabeledError> {
+        let rt = tokio::runtime::Runtime::new()
+            .map_err(|e| LabeledError::new(format!("Failed to create runtime: {e}")))?;
+
+        let repo: String = call.req(0)?;
+        let repo_span = call.positional[0].span();
+        let (reference, client) = registry_client(&repo, repo_span)?;
+        if reference.reference.is_some() {
+            return Err(LabeledError::new("Invalid repository")
+                .with_label("Expected a repository without tag or digest", repo_span));
+        }
+
+        let tags = rt.block_on(client.tags()).map_err(|e| {
+            LabeledError::new("Failed to list the tags").with_label(e.to_string(), repo_span)
+        })?;
+
+        if let Some(timeout) = plugin.timeout {
+            rt.shutdown_timeout(timeout);
+            return Err(LabeledError::new(format!(
+                "Timeout: Operation time exceeded {} seconds",
+                timeout.as_secs()
+            )));
+        }
+
+        let span = call.head;
+        let tags = tags
+            .into_iter()
+            .map(|tag| Value::string(tag, span))
+            .collect();
+        Ok(Value::list(tags, span).into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<nu_protocol::Example<'_>> {
+        vec![
+            Example {
+                description: "List the tags of the official alpine image",
+                example: "ndocker registry tags alpine",
+                result: None,
+            },
+            Example {
+                description: "List the release tags of a repository of a local registry",
+                example: "ndocker registry tags localhost:5000/app | where $it =~ '^v\\d'",
+                result: None,
+            },
+        ]
+    }
+}
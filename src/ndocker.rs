@@ -10,6 +10,12 @@ pub struct NdockerPlugin {
     pub timeout: Option<std::time::Duration>,
 }
 
+impl Default for NdockerPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl NdockerPlugin {
     pub fn new() -> Self {
         NdockerPlugin {
@@ -23,10 +29,25 @@ impl NdockerPlugin {
 impl Plugin for NdockerPlugin {
     fn commands(&self) -> Vec<Box<dyn PluginCommand<Plugin = Self>>> {
         vec![
+            Box::new(container::ps::PsCommand),
             Box::new(image::images::ImagesCommand),
             Box::new(image::history::ImageHistoryCommand),
             Box::new(image::inspect::ImageInspectCommand),
             Box::new(image::import::ImageImportCommand),
+            Box::new(image::pull::ImagePullCommand),
+            Box::new(image::push::ImagePushCommand),
+            Box::new(image::tag::ImageTagCommand),
+            Box::new(image::rm::ImageRmCommand),
+            Box::new(image::prune::ImagePruneCommand),
+            Box::new(image::save::ImageSaveCommand),
+            Box::new(image::load::ImageLoadCommand),
+            Box::new(image::layers::ImageLayersCommand),
+            Box::new(image::diff::ImageDiffCommand),
+            Box::new(image::files::ImageFilesCommand),
+            Box::new(image::build::ImageBuildCommand),
+            Box::new(image::search::ImageSearchCommand),
+            Box::new(registry::tags::RegistryTagsCommand),
+            Box::new(registry::manifest::RegistryManifestCommand),
         ]
     }
 
@@ -1,3 +1,9 @@
 //! Utility functions for the ndocker plugin.
+pub mod auth;
+pub mod dockerfile;
+pub mod dockerignore;
 pub mod file;
 pub mod net;
+pub mod registry;
+#[cfg(test)]
+pub mod test_server;
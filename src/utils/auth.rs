@@ -0,0 +1,210 @@
+//! Utility functions for registry credentials in the plugin.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use bollard::auth::DockerCredentials;
+use serde::Deserialize;
+
+/// The key used for Docker Hub in `~/.docker/config.json`.
+pub const DOCKER_HUB_AUTH_KEY: &str = "https://index.docker.io/v1/";
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub enum AuthErrorType {
+    ConfigError,
+    HelperError,
+}
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct AuthError {
+    pub error_type: AuthErrorType,
+    pub message: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    auths: HashMap<String, AuthEntry>,
+    #[serde(default, rename = "credsStore")]
+    creds_store: Option<String>,
+    #[serde(default, rename = "credHelpers")]
+    cred_helpers: HashMap<String, String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct AuthEntry {
+    auth: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+    identitytoken: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HelperOutput {
+    #[serde(rename = "Username")]
+    username: String,
+    #[serde(rename = "Secret")]
+    secret: String,
+}
+
+/// Get the registry host of an image name, e.g. `localhost:5000` for
+/// `localhost:5000/rust:1.84.0` and `docker.io` for `rust:1.84.0`.
+pub fn registry_host(name: &str) -> String {
+    match name.split_once('/') {
+        Some((host, _)) if host.contains('.') || host.contains(':') || host == "localhost" => {
+            host.to_string()
+        }
+        _ => "docker.io".to_string(),
+    }
+}
+
+fn config_path() -> Option<PathBuf> {
+    if let Ok(dir) = std::env::var("DOCKER_CONFIG") {
+        return Some(PathBuf::from(dir).join("config.json"));
+    }
+    std::env::var("HOME")
+        .ok()
+        .map(|home| PathBuf::from(home).join(".docker").join("config.json"))
+}
+
+/// Strip the scheme and the path of a key in `auths`, so that
+/// `https://index.docker.io/v1/` and `index.docker.io` are the same host.
+fn normalize_host(key: &str) -> &str {
+    let key = key
+        .strip_prefix("https://")
+        .or_else(|| key.strip_prefix("http://"))
+        .unwrap_or(key);
+    let host = key.split('/').next().unwrap_or(key);
+    match host {
+        "index.docker.io" | "registry-1.docker.io" => "docker.io",
+        _ => host,
+    }
+}
+
+/// Read the credentials for `host` from the docker config file, using the
+/// credential helpers configured there if any.
+/// Returns `None` if there is no config file or no credentials for the host.
+pub fn credentials_from_config(host: &str) -> Result<Option<DockerCredentials>, AuthError> {
+    let Some(path) = config_path() else {
+        return Ok(None);
+    };
+    let Ok(content) = std::fs::read_to_string(&path) else {
+        return Ok(None);
+    };
+    let config: ConfigFile = serde_json::from_str(&content).map_err(|e| AuthError {
+        error_type: AuthErrorType::ConfigError,
+        message: format!("Failed to parse {}: {}", path.display(), e),
+    })?;
+
+    let server_address = if host == "docker.io" {
+        DOCKER_HUB_AUTH_KEY.to_string()
+    } else {
+        host.to_string()
+    };
+
+    let helper = config
+        .cred_helpers
+        .iter()
+        .find(|(key, _)| normalize_host(key) == host)
+        .map(|(_, helper)| helper)
+        .or(config.creds_store.as_ref());
+    if let Some(helper) = helper
+        && let Some(credentials) = credentials_from_helper(helper, &server_address)?
+    {
+        return Ok(Some(credentials));
+    }
+
+    let Some(entry) = config
+        .auths
+        .iter()
+        .find(|(key, _)| normalize_host(key) == host)
+        .map(|(_, entry)| entry)
+    else {
+        return Ok(None);
+    };
+
+    let (mut username, mut password) = (entry.username.clone(), entry.password.clone());
+    if let Some(auth) = &entry.auth {
+        let decoded = STANDARD
+            .decode(auth.trim())
+            .ok()
+            .and_then(|decoded| String::from_utf8(decoded).ok())
+            .ok_or_else(|| AuthError {
+                error_type: AuthErrorType::ConfigError,
+                message: format!("Invalid auth entry for {} in {}", host, path.display()),
+            })?;
+        if let Some((user, pass)) = decoded.split_once(':') {
+            username = Some(user.to_string());
+            password = Some(pass.to_string());
+        }
+    }
+
+    Ok(Some(DockerCredentials {
+        username,
+        password,
+        identitytoken: entry.identitytoken.clone(),
+        serveraddress: Some(server_address),
+        ..Default::default()
+    }))
+}
+
+/// Ask `docker-credential-<helper>` for the credentials of `server_address`.
+fn credentials_from_helper(
+    helper: &str,
+    server_address: &str,
+) -> Result<Option<DockerCredentials>, AuthError> {
+    let program = format!("docker-credential-{helper}");
+    let mut child = Command::new(&program)
+        .arg("get")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| AuthError {
+            error_type: AuthErrorType::HelperError,
+            message: format!("Failed to run {program}: {e}"),
+        })?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(server_address.as_bytes());
+    }
+    let output = child.wait_with_output().map_err(|e| AuthError {
+        error_type: AuthErrorType::HelperError,
+        message: format!("Failed to run {program}: {e}"),
+    })?;
+    // The helper exits with an error when it has no credentials for the server.
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let output: HelperOutput = serde_json::from_slice(&output.stdout).map_err(|e| AuthError {
+        error_type: AuthErrorType::HelperError,
+        message: format!("Invalid output of {program}: {e}"),
+    })?;
+
+    // A username of `<token>` means the secret is an identity token.
+    if output.username == "<token>" {
+        Ok(Some(DockerCredentials {
+            identitytoken: Some(output.secret),
+            serveraddress: Some(server_address.to_string()),
+            ..Default::default()
+        }))
+    } else {
+        Ok(Some(DockerCredentials {
+            username: Some(output.username),
+            password: Some(output.secret),
+            serveraddress: Some(server_address.to_string()),
+            ..Default::default()
+        }))
+    }
+}
+
+impl std::fmt::Display for AuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
@@ -0,0 +1,634 @@
+//! Utility functions for parsing Dockerfile instructions in the plugin.
+//!
+//! Only the instructions the daemon accepts as changes when importing or
+//! committing an image are supported, along with the reconstruction of a
+//! Dockerfile from the history of an image.
+
+use std::collections::HashSet;
+
+/// The instructions accepted as changes by the daemon.
+pub const CHANGE_INSTRUCTIONS: &[&str] = &[
+    "CMD",
+    "ENTRYPOINT",
+    "ENV",
+    "EXPOSE",
+    "LABEL",
+    "ONBUILD",
+    "USER",
+    "VOLUME",
+    "WORKDIR",
+];
+
+/// All the instructions of a Dockerfile, used to check the trigger of `ONBUILD`.
+const ALL_INSTRUCTIONS: &[&str] = &[
+    "ADD",
+    "ARG",
+    "CMD",
+    "COPY",
+    "ENTRYPOINT",
+    "ENV",
+    "EXPOSE",
+    "FROM",
+    "HEALTHCHECK",
+    "LABEL",
+    "MAINTAINER",
+    "ONBUILD",
+    "RUN",
+    "SHELL",
+    "STOPSIGNAL",
+    "USER",
+    "VOLUME",
+    "WORKDIR",
+];
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub enum DockerfileErrorType {
+    SyntaxError,
+    UnsupportedInstruction,
+}
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct DockerfileError {
+    pub error_type: DockerfileErrorType,
+    pub message: String,
+}
+
+/// The arguments of `CMD` and `ENTRYPOINT`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandForm {
+    /// `["executable", "param"]`
+    Exec(Vec<String>),
+    /// `executable param`, run with `/bin/sh -c`
+    Shell(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    Cmd(CommandForm),
+    Entrypoint(CommandForm),
+    Env(Vec<(String, String)>),
+    Expose(Vec<String>),
+    Label(Vec<(String, String)>),
+    Onbuild(String),
+    User(String),
+    Volume(Vec<String>),
+    Workdir(String),
+}
+
+fn syntax_error(message: String) -> DockerfileError {
+    DockerfileError {
+        error_type: DockerfileErrorType::SyntaxError,
+        message,
+    }
+}
+
+/// Join the lines of an instruction continued with a trailing backslash.
+fn join_continuations(line: &str) -> String {
+    let mut joined = String::new();
+    for part in line.split('\n') {
+        match part.trim_end().strip_suffix('\\') {
+            Some(continued) => joined.push_str(continued),
+            None => {
+                joined.push_str(part);
+                joined.push('\n');
+            }
+        }
+    }
+    joined
+}
+
+/// Parse one instruction, e.g. `ENV PATH=/usr/local/bin:$PATH`, which may be
+/// continued over several lines with a trailing backslash.
+pub fn parse_instruction(line: &str) -> Result<Instruction, DockerfileError> {
+    let line = join_continuations(line);
+    let line = line.trim();
+    let (keyword, args) = line
+        .split_once(char::is_whitespace)
+        .map(|(keyword, args)| (keyword, args.trim()))
+        .unwrap_or((line, ""));
+    let keyword = keyword.to_uppercase();
+    if !CHANGE_INSTRUCTIONS.contains(&keyword.as_str()) {
+        return Err(DockerfileError {
+            error_type: DockerfileErrorType::UnsupportedInstruction,
+            message: format!(
+                "Unsupported instruction {}, expected one of {{{}}}",
+                keyword,
+                CHANGE_INSTRUCTIONS.join(", ")
+            ),
+        });
+    }
+    if args.is_empty() {
+        return Err(syntax_error(format!(
+            "{} requires at least one argument",
+            keyword
+        )));
+    }
+
+    match keyword.as_str() {
+        "CMD" => parse_command_form(args).map(Instruction::Cmd),
+        "ENTRYPOINT" => parse_command_form(args).map(Instruction::Entrypoint),
+        "ENV" => parse_env(args).map(Instruction::Env),
+        "EXPOSE" => parse_expose(args).map(Instruction::Expose),
+        "LABEL" => parse_key_values("LABEL", args).map(Instruction::Label),
+        "ONBUILD" => parse_onbuild(args).map(Instruction::Onbuild),
+        "USER" => parse_user(args).map(Instruction::User),
+        "VOLUME" => parse_volume(args).map(Instruction::Volume),
+        _ => Ok(Instruction::Workdir(args.to_string())),
+    }
+}
+
+/// Parse a JSON array of strings, `None` if `args` isn't in JSON form.
+fn parse_json_form(args: &str) -> Result<Option<Vec<String>>, DockerfileError> {
+    if !args.starts_with('[') {
+        return Ok(None);
+    }
+    serde_json::from_str::<Vec<String>>(args)
+        .map(Some)
+        .map_err(|e| {
+            syntax_error(format!(
+                "Invalid JSON form, expected an array of strings: {}",
+                e
+            ))
+        })
+}
+
+fn parse_command_form(args: &str) -> Result<CommandForm, DockerfileError> {
+    match parse_json_form(args)? {
+        Some(words) if words.is_empty() => Err(syntax_error(
+            "The JSON form requires at least one element".to_string(),
+        )),
+        Some(words) => Ok(CommandForm::Exec(words)),
+        None => Ok(CommandForm::Shell(args.to_string())),
+    }
+}
+
+/// Split `args` into words separated by whitespace, removing the quotes and
+/// the escaping backslashes.
+fn split_words(args: &str) -> Result<Vec<String>, DockerfileError> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+    let mut chars = args.chars();
+    while let Some(c) = chars.next() {
+        match (c, quote) {
+            ('\\', Some('\'')) => word.push(c),
+            ('\\', _) => {
+                if let Some(escaped) = chars.next() {
+                    word.push(escaped);
+                }
+                in_word = true;
+            }
+            (c, Some(q)) if c == q => quote = None,
+            (c, Some(_)) => word.push(c),
+            ('"' | '\'', None) => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (c, None) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (c, None) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if let Some(q) = quote {
+        return Err(syntax_error(format!("Unterminated quote {}", q)));
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+fn parse_key_values(keyword: &str, args: &str) -> Result<Vec<(String, String)>, DockerfileError> {
+    split_words(args)?
+        .into_iter()
+        .map(|word| match word.split_once('=') {
+            Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+            _ => Err(syntax_error(format!(
+                "{} expects key=value pairs, found: {}",
+                keyword, word
+            ))),
+        })
+        .collect()
+}
+
+fn parse_env(args: &str) -> Result<Vec<(String, String)>, DockerfileError> {
+    let first = args.split_whitespace().next().unwrap_or_default();
+    if first.contains('=') {
+        return parse_key_values("ENV", args);
+    }
+    // The legacy form `ENV key value` sets a single variable.
+    match args.split_once(char::is_whitespace) {
+        Some((key, value)) => Ok(vec![(key.to_string(), value.trim().to_string())]),
+        None => Err(syntax_error(format!("ENV {} must have a value", args))),
+    }
+}
+
+fn parse_expose(args: &str) -> Result<Vec<String>, DockerfileError> {
+    args.split_whitespace()
+        .map(|port| {
+            let (range, protocol) = port.split_once('/').unwrap_or((port, "tcp"));
+            let ports = range.split('-').collect::<Vec<_>>();
+            let valid_range =
+                ports.len() <= 2 && ports.iter().all(|p| p.parse::<u16>().is_ok_and(|p| p > 0));
+            let valid_protocol = ["tcp", "udp", "sctp"].contains(&protocol.to_lowercase().as_str());
+            if valid_range && valid_protocol {
+                Ok(port.to_string())
+            } else {
+                Err(syntax_error(format!(
+                    "Invalid port {}, expected port[-port][/tcp|udp|sctp]",
+                    port
+                )))
+            }
+        })
+        .collect()
+}
+
+fn parse_onbuild(args: &str) -> Result<String, DockerfileError> {
+    let (trigger, trigger_args) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    let trigger = trigger.to_uppercase();
+    match trigger.as_str() {
+        "ONBUILD" | "FROM" | "MAINTAINER" => Err(syntax_error(format!(
+            "{} isn't allowed as an ONBUILD trigger",
+            trigger
+        ))),
+        _ if !ALL_INSTRUCTIONS.contains(&trigger.as_str()) => {
+            Err(syntax_error(format!("Unknown ONBUILD trigger {}", trigger)))
+        }
+        _ if trigger_args.trim().is_empty() => Err(syntax_error(format!(
+            "{} requires at least one argument",
+            trigger
+        ))),
+        _ => Ok(args.to_string()),
+    }
+}
+
+fn parse_user(args: &str) -> Result<String, DockerfileError> {
+    if args.contains(char::is_whitespace) {
+        return Err(syntax_error(format!(
+            "Invalid user {}, expected user[:group]",
+            args
+        )));
+    }
+    Ok(args.to_string())
+}
+
+fn parse_volume(args: &str) -> Result<Vec<String>, DockerfileError> {
+    let volumes = match parse_json_form(args)? {
+        Some(volumes) => volumes,
+        None => split_words(args)?,
+    };
+    if volumes.iter().any(|volume| volume.trim().is_empty()) {
+        return Err(syntax_error("VOLUME paths can't be empty".to_string()));
+    }
+    Ok(volumes)
+}
+
+/// The shells the builders prefix the `RUN` commands with.
+const RUN_SHELLS: &[&str] = &["/bin/sh -c ", "cmd /S /C "];
+
+/// Split the `|N key=value ...` marker of the build arguments a `RUN` step
+/// used off its command.
+fn split_build_args(command: &str) -> (Vec<(String, String)>, &str) {
+    let Some(marker) = command.strip_prefix('|') else {
+        return (Vec::new(), command);
+    };
+    let Some((count, mut rest)) = marker.split_once(' ') else {
+        return (Vec::new(), command);
+    };
+    let Ok(count) = count.parse::<usize>() else {
+        return (Vec::new(), command);
+    };
+    let mut args = Vec::with_capacity(count);
+    for _ in 0..count {
+        let (arg, remaining) = rest.split_once(' ').unwrap_or((rest, ""));
+        let (key, value) = arg.split_once('=').unwrap_or((arg, ""));
+        args.push((key.to_string(), value.to_string()));
+        rest = remaining;
+    }
+    (args, rest)
+}
+
+/// Turn the `created_by` of a history step into a Dockerfile instruction, with
+/// the build arguments it used. `None` for the steps without an instruction,
+/// like the ones of an imported image.
+///
+/// Handles the classic builder, which writes `/bin/sh -c #(nop)  CMD [...]`
+/// for the instructions other than `RUN`, and BuildKit, which writes the
+/// instruction itself followed by `# buildkit`.
+pub fn instruction_from_history(created_by: &str) -> Option<(Vec<(String, String)>, String)> {
+    let created_by = created_by.trim();
+    let created_by = created_by
+        .strip_suffix("# buildkit")
+        .unwrap_or(created_by)
+        .trim_end();
+    if created_by.is_empty() {
+        return None;
+    }
+    let (args, command) = split_build_args(created_by.strip_prefix("RUN ").unwrap_or(created_by));
+    let is_run =
+        created_by.starts_with("RUN ") || RUN_SHELLS.iter().any(|shell| command.starts_with(shell));
+
+    let instruction = match RUN_SHELLS
+        .iter()
+        .find_map(|shell| command.strip_prefix(shell))
+    {
+        Some(command) => match command.trim_start().strip_prefix("#(nop)") {
+            Some(instruction) => instruction.trim().to_string(),
+            None => format!("RUN {}", command.trim()),
+        },
+        None if is_run => format!("RUN {}", command.trim()),
+        None => created_by.to_string(),
+    };
+
+    let (keyword, arguments) = instruction
+        .split_once(char::is_whitespace)
+        .unwrap_or((&instruction, ""));
+    let arguments = arguments.trim();
+    let instruction = match keyword {
+        // `ADD file:<digest> in /` for the files added by the builder.
+        "ADD" | "COPY" => match arguments.rsplit_once(" in ") {
+            Some((source, destination)) => {
+                format!("{} {} {}", keyword, source.trim(), destination.trim())
+            }
+            None => instruction.clone(),
+        },
+        // `EXPOSE map[80/tcp:{} 443/tcp:{}]` for the ports set by the builder.
+        "EXPOSE" => match arguments
+            .strip_prefix("map[")
+            .and_then(|ports| ports.strip_suffix(']'))
+        {
+            Some(ports) => format!(
+                "EXPOSE {}",
+                ports
+                    .split_whitespace()
+                    .map(|port| port.trim_end_matches(":{}"))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+            None => instruction.clone(),
+        },
+        _ => instruction.clone(),
+    };
+    Some((args, instruction))
+}
+
+/// Reconstruct an approximate Dockerfile from the `created_by` of the history
+/// steps of an image, oldest first.
+///
+/// The history covers the base images as well, so it starts from `scratch`.
+/// The build arguments are declared before the first step using them, with
+/// the value they had during the build.
+pub fn dockerfile_from_history<'a>(steps: impl IntoIterator<Item = &'a str>) -> String {
+    let mut lines = vec!["FROM scratch".to_string()];
+    let mut declared = HashSet::new();
+    for (args, instruction) in steps.into_iter().filter_map(instruction_from_history) {
+        for (key, value) in args {
+            if declared.insert(key.clone()) {
+                lines.push(format!("ARG {}={}", key, value));
+            }
+        }
+        if let Some(key) = instruction.strip_prefix("ARG ") {
+            let key = key.split('=').next().unwrap_or_default().trim();
+            if !declared.insert(key.to_string()) {
+                continue;
+            }
+        }
+        lines.push(instruction);
+    }
+    lines.join("\n") + "\n"
+}
+
+impl std::fmt::Display for DockerfileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn error_message(line: &str) -> String {
+        parse_instruction(line).unwrap_err().message
+    }
+
+    #[test]
+    fn parse_instruction_joins_continued_lines() {
+        assert_eq!(
+            parse_instruction("ENV A=1 \\\n    B=2 \\\n    C=3").unwrap(),
+            Instruction::Env(pairs(&[("A", "1"), ("B", "2"), ("C", "3")]))
+        );
+        assert_eq!(
+            parse_instruction("CMD [\"nginx\", \\\n  \"-g\", \"daemon off;\"]").unwrap(),
+            Instruction::Cmd(CommandForm::Exec(vec![
+                "nginx".to_string(),
+                "-g".to_string(),
+                "daemon off;".to_string(),
+            ]))
+        );
+        assert_eq!(
+            parse_instruction("label \\\n version=1.0").unwrap(),
+            Instruction::Label(pairs(&[("version", "1.0")]))
+        );
+    }
+
+    #[test]
+    fn parse_instruction_tells_json_from_shell_form() {
+        assert_eq!(
+            parse_instruction(r#"ENTRYPOINT ["/docker-entrypoint.sh", "--"]"#).unwrap(),
+            Instruction::Entrypoint(CommandForm::Exec(vec![
+                "/docker-entrypoint.sh".to_string(),
+                "--".to_string(),
+            ]))
+        );
+        assert_eq!(
+            parse_instruction("CMD echo \"hello world\"").unwrap(),
+            Instruction::Cmd(CommandForm::Shell("echo \"hello world\"".to_string()))
+        );
+        assert_eq!(
+            parse_instruction(r#"VOLUME ["/data", "/var/log"]"#).unwrap(),
+            Instruction::Volume(vec!["/data".to_string(), "/var/log".to_string()])
+        );
+        assert_eq!(
+            parse_instruction("VOLUME /data '/with space'").unwrap(),
+            Instruction::Volume(vec!["/data".to_string(), "/with space".to_string()])
+        );
+        assert!(error_message("CMD []").contains("at least one element"));
+        assert!(error_message("CMD [\"nginx\", 1]").contains("Invalid JSON form"));
+    }
+
+    #[test]
+    fn parse_instruction_reads_key_values() {
+        assert_eq!(
+            parse_instruction(r#"ENV GREETING="hello world" PATH=/usr/bin\ x"#).unwrap(),
+            Instruction::Env(pairs(&[
+                ("GREETING", "hello world"),
+                ("PATH", "/usr/bin x")
+            ]))
+        );
+        assert_eq!(
+            parse_instruction("ENV JAVA_HOME /opt/java 21").unwrap(),
+            Instruction::Env(pairs(&[("JAVA_HOME", "/opt/java 21")]))
+        );
+        assert_eq!(
+            parse_instruction(r#"LABEL description='a \ b'"#).unwrap(),
+            Instruction::Label(pairs(&[("description", r"a \ b")]))
+        );
+        assert!(error_message("ENV PATH").contains("must have a value"));
+        assert!(error_message("LABEL version").contains("key=value"));
+        assert!(error_message("LABEL a=\"b").contains("Unterminated quote"));
+    }
+
+    #[test]
+    fn parse_instruction_checks_the_arguments() {
+        assert_eq!(
+            parse_instruction("EXPOSE 80 8000-8080/udp").unwrap(),
+            Instruction::Expose(vec!["80".to_string(), "8000-8080/udp".to_string()])
+        );
+        assert!(error_message("EXPOSE 0").contains("Invalid port"));
+        assert!(error_message("EXPOSE 80/http").contains("Invalid port"));
+        assert_eq!(
+            parse_instruction("USER app:app").unwrap(),
+            Instruction::User("app:app".to_string())
+        );
+        assert!(error_message("USER app app").contains("Invalid user"));
+        assert_eq!(
+            parse_instruction("ONBUILD RUN make").unwrap(),
+            Instruction::Onbuild("RUN make".to_string())
+        );
+        assert!(error_message("ONBUILD FROM alpine").contains("isn't allowed"));
+        assert!(error_message("ONBUILD BUILD make").contains("Unknown ONBUILD trigger"));
+        assert!(error_message("WORKDIR").contains("at least one argument"));
+    }
+
+    #[test]
+    fn parse_instruction_rejects_build_instructions() {
+        let error = parse_instruction("RUN apt-get update").unwrap_err();
+        assert!(matches!(
+            error.error_type,
+            DockerfileErrorType::UnsupportedInstruction
+        ));
+        assert!(error.message.starts_with("Unsupported instruction RUN"));
+    }
+
+    #[test]
+    fn split_build_args_reads_the_marker() {
+        assert_eq!(
+            split_build_args("|2 A=1 B=2 /bin/sh -c make"),
+            (pairs(&[("A", "1"), ("B", "2")]), "/bin/sh -c make")
+        );
+        assert_eq!(
+            split_build_args("|1 EMPTY= /bin/sh -c make"),
+            (pairs(&[("EMPTY", "")]), "/bin/sh -c make")
+        );
+        assert_eq!(
+            split_build_args("/bin/sh -c make"),
+            (Vec::new(), "/bin/sh -c make")
+        );
+        assert_eq!(split_build_args("|x A=1 make"), (Vec::new(), "|x A=1 make"));
+    }
+
+    #[test]
+    fn instruction_from_history_of_the_classic_builder() {
+        let instruction = |created_by| instruction_from_history(created_by).unwrap();
+        assert_eq!(
+            instruction("/bin/sh -c #(nop)  CMD [\"sh\"]"),
+            (Vec::new(), "CMD [\"sh\"]".to_string())
+        );
+        assert_eq!(
+            instruction(
+                "/bin/sh -c #(nop) ADD file:9a4f77dfaba7fd2aa78186e4ef0e7486ad55101cefc1fabbc1b385601bb38920 in / "
+            ),
+            (
+                Vec::new(),
+                "ADD file:9a4f77dfaba7fd2aa78186e4ef0e7486ad55101cefc1fabbc1b385601bb38920 /"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            instruction("/bin/sh -c #(nop)  EXPOSE map[443/tcp:{} 80/tcp:{}]"),
+            (Vec::new(), "EXPOSE 443/tcp 80/tcp".to_string())
+        );
+        assert_eq!(
+            instruction("/bin/sh -c apk add --no-cache curl"),
+            (Vec::new(), "RUN apk add --no-cache curl".to_string())
+        );
+        assert_eq!(
+            instruction("|2 A=1 B=2 /bin/sh -c echo $A $B"),
+            (
+                pairs(&[("A", "1"), ("B", "2")]),
+                "RUN echo $A $B".to_string()
+            )
+        );
+        assert_eq!(
+            instruction("cmd /S /C powershell -Command Write-Host hi"),
+            (
+                Vec::new(),
+                "RUN powershell -Command Write-Host hi".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn instruction_from_history_of_buildkit() {
+        let instruction = |created_by| instruction_from_history(created_by).unwrap();
+        assert_eq!(
+            instruction("RUN |2 A=1 B=2 /bin/sh -c make # buildkit"),
+            (pairs(&[("A", "1"), ("B", "2")]), "RUN make".to_string())
+        );
+        assert_eq!(
+            instruction("RUN /bin/sh -c apt-get update # buildkit"),
+            (Vec::new(), "RUN apt-get update".to_string())
+        );
+        assert_eq!(
+            instruction("COPY app /usr/src/app # buildkit"),
+            (Vec::new(), "COPY app /usr/src/app".to_string())
+        );
+        assert_eq!(
+            instruction("WORKDIR /usr/src/app"),
+            (Vec::new(), "WORKDIR /usr/src/app".to_string())
+        );
+        assert_eq!(instruction_from_history(""), None);
+        assert_eq!(instruction_from_history(" # buildkit"), None);
+    }
+
+    #[test]
+    fn dockerfile_from_history_declares_the_build_args_once() {
+        let dockerfile = dockerfile_from_history([
+            "/bin/sh -c #(nop) ADD file:abc in / ",
+            "/bin/sh -c #(nop)  CMD [\"sh\"]",
+            "ARG VERSION=1.0",
+            "",
+            "|1 VERSION=1.0 /bin/sh -c echo $VERSION > /version",
+            "RUN |2 VERSION=1.0 TARGET=release /bin/sh -c make $TARGET # buildkit",
+            "ARG TARGET=release",
+        ]);
+        assert_eq!(
+            dockerfile,
+            "FROM scratch\n\
+             ADD file:abc /\n\
+             CMD [\"sh\"]\n\
+             ARG VERSION=1.0\n\
+             RUN echo $VERSION > /version\n\
+             ARG TARGET=release\n\
+             RUN make $TARGET\n"
+        );
+    }
+}
@@ -0,0 +1,333 @@
+//! Utility functions for reading the `.dockerignore` file of a build context.
+//!
+//! The rules follow the daemon: `*` and `?` don't match `/`, `**` matches any
+//! number of directories, a rule starting with `!` brings back what an earlier
+//! rule left out, and the last matching rule wins. A rule matching a directory
+//! applies to everything under it.
+
+use std::path::Path;
+
+use regex::Regex;
+
+use crate::utils::file::ExcludeRules;
+
+/// The name of the file listing the paths left out of a build context.
+pub const DOCKERIGNORE_FILE: &str = ".dockerignore";
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub enum DockerignoreErrorType {
+    FileError,
+    PatternError,
+}
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct DockerignoreError {
+    pub error_type: DockerignoreErrorType,
+    pub message: String,
+}
+
+/// A line of a `.dockerignore` file.
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: String,
+    regex: Regex,
+    negated: bool,
+}
+
+/// The rules of a `.dockerignore` file, in file order.
+#[derive(Debug, Clone, Default)]
+pub struct Dockerignore {
+    rules: Vec<Rule>,
+}
+
+/// Clean a pattern like a path: no `.` or empty components, `..` resolved,
+/// and relative to the root of the context.
+fn clean_pattern(pattern: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in pattern.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+/// Translate a pattern into a regular expression matching whole paths.
+fn pattern_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    let mut expression = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    let mut in_class = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if !in_class && chars.peek() == Some(&'*') => {
+                chars.next();
+                // `**/` matches no directory as well.
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                }
+                if chars.peek().is_none() {
+                    expression.push_str(".*");
+                } else {
+                    expression.push_str("(.*/)?");
+                }
+            }
+            '*' if !in_class => expression.push_str("[^/]*"),
+            '?' if !in_class => expression.push_str("[^/]"),
+            '[' if !in_class => {
+                in_class = true;
+                expression.push('[');
+            }
+            ']' if in_class => {
+                in_class = false;
+                expression.push(']');
+            }
+            '\\' => match chars.next() {
+                Some(escaped) => expression.push_str(&regex::escape(&escaped.to_string())),
+                None => expression.push_str("\\\\"),
+            },
+            c if in_class => expression.push(c),
+            c => expression.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    expression.push('$');
+    Regex::new(&expression)
+}
+
+impl Dockerignore {
+    /// Parse the content of a `.dockerignore` file. Comments and empty lines
+    /// are skipped.
+    pub fn parse(content: &str) -> Result<Self, DockerignoreError> {
+        let mut dockerignore = Self::default();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (negated, pattern) = match line.strip_prefix('!') {
+                Some(pattern) => (true, pattern.trim()),
+                None => (false, line),
+            };
+            dockerignore
+                .add_rule(pattern, negated)
+                .map_err(|e| DockerignoreError {
+                    error_type: DockerignoreErrorType::PatternError,
+                    message: format!(
+                        "{}:{}: invalid pattern {}: {}",
+                        DOCKERIGNORE_FILE,
+                        number + 1,
+                        line,
+                        e
+                    ),
+                })?;
+        }
+        Ok(dockerignore)
+    }
+
+    /// Read the `.dockerignore` file at the root of the build context, no
+    /// rules if there is no such file.
+    pub fn read(root: &Path) -> Result<Self, DockerignoreError> {
+        let path = root.join(DOCKERIGNORE_FILE);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path).map_err(|e| DockerignoreError {
+            error_type: DockerignoreErrorType::FileError,
+            message: format!("{}: {}", path.display(), e),
+        })?;
+        Self::parse(&content)
+    }
+
+    fn add_rule(&mut self, pattern: &str, negated: bool) -> Result<(), regex::Error> {
+        let pattern = clean_pattern(pattern);
+        if pattern.is_empty() {
+            return Ok(());
+        }
+        let regex = pattern_regex(&pattern)?;
+        self.rules.push(Rule {
+            pattern,
+            regex,
+            negated,
+        });
+        Ok(())
+    }
+
+    /// Send `path` whatever the rules, like the daemon does for the
+    /// Dockerfile and the `.dockerignore` file.
+    pub fn keep(&mut self, path: &str) {
+        // An escaped path can't be an invalid pattern.
+        let escaped = path
+            .chars()
+            .map(|c| match c {
+                '*' | '?' | '[' | ']' | '\\' => format!("\\{}", c),
+                c => c.to_string(),
+            })
+            .collect::<String>();
+        let _ = self.add_rule(&escaped, true);
+    }
+}
+
+impl ExcludeRules for Dockerignore {
+    fn excludes(&self, path: &Path) -> bool {
+        let path = path.to_string_lossy().replace('\\', "/");
+        let parents = path
+            .match_indices('/')
+            .map(|(index, _)| &path[..index])
+            .collect::<Vec<_>>();
+        let mut excluded = false;
+        for rule in &self.rules {
+            // Only the rules that could change the outcome are checked.
+            if rule.negated != excluded {
+                continue;
+            }
+            let matches = rule.regex.is_match(&path)
+                || parents.iter().any(|parent| rule.regex.is_match(parent));
+            if matches {
+                excluded = !rule.negated;
+            }
+        }
+        excluded
+    }
+
+    fn walks_excluded(&self, path: &Path) -> bool {
+        let directory = format!("{}/", path.to_string_lossy().replace('\\', "/"));
+        self.rules
+            .iter()
+            .any(|rule| rule.negated && format!("{}/", rule.pattern).starts_with(&directory))
+    }
+}
+
+impl std::fmt::Display for DockerignoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn excluded(rules: &str, path: &str) -> bool {
+        Dockerignore::parse(rules)
+            .unwrap()
+            .excludes(Path::new(path))
+    }
+
+    #[test]
+    fn clean_pattern_resolves_the_components() {
+        assert_eq!(clean_pattern("./src//main.rs"), "src/main.rs");
+        assert_eq!(clean_pattern("/build/"), "build");
+        assert_eq!(clean_pattern("a/b/../c"), "a/c");
+        assert_eq!(clean_pattern("../../secret"), "secret");
+        assert_eq!(clean_pattern("a/.."), "");
+    }
+
+    #[test]
+    fn pattern_regex_keeps_the_stars_within_a_directory() {
+        let regex = pattern_regex("*.log").unwrap();
+        assert!(regex.is_match("debug.log"));
+        assert!(!regex.is_match("logs/debug.log"));
+        assert!(!regex.is_match("debug.logs"));
+
+        let regex = pattern_regex("file?.txt").unwrap();
+        assert!(regex.is_match("file1.txt"));
+        assert!(!regex.is_match("file/.txt"));
+        assert!(pattern_regex("[ab].txt").unwrap().is_match("b.txt"));
+    }
+
+    #[test]
+    fn pattern_regex_double_star_matches_any_directory() {
+        let regex = pattern_regex("**/*.log").unwrap();
+        assert!(regex.is_match("debug.log"));
+        assert!(regex.is_match("a/b/debug.log"));
+
+        let regex = pattern_regex("src/**/test").unwrap();
+        assert!(regex.is_match("src/test"));
+        assert!(regex.is_match("src/a/b/test"));
+        assert!(!regex.is_match("src/atest"));
+
+        let regex = pattern_regex("build/**").unwrap();
+        assert!(regex.is_match("build/a/b.o"));
+        assert!(!regex.is_match("build"));
+        assert!(!regex.is_match("builds/a"));
+    }
+
+    #[test]
+    fn pattern_regex_escapes_the_metacharacters() {
+        let regex = pattern_regex("\\*.txt").unwrap();
+        assert!(regex.is_match("*.txt"));
+        assert!(!regex.is_match("a.txt"));
+        assert!(pattern_regex("a\\[1\\].txt").unwrap().is_match("a[1].txt"));
+        assert!(pattern_regex("a+(b).txt").unwrap().is_match("a+(b).txt"));
+        assert!(pattern_regex("a.txt").unwrap().is_match("a.txt"));
+        assert!(!pattern_regex("a.txt").unwrap().is_match("abtxt"));
+    }
+
+    #[test]
+    fn excludes_everything_under_a_directory() {
+        assert!(excluded("build", "build"));
+        assert!(excluded("build", "build/a/b.o"));
+        assert!(!excluded("build", "builds/a"));
+        assert!(excluded("**/node_modules", "web/node_modules/x/index.js"));
+        assert!(excluded("/tmp/../cache/", "cache/index"));
+        assert!(!excluded("# build\n\n", "build"));
+    }
+
+    #[test]
+    fn excludes_lets_the_last_matching_rule_win() {
+        let rules = "build\n!build/keep.txt\n";
+        assert!(excluded(rules, "build/other.txt"));
+        assert!(!excluded(rules, "build/keep.txt"));
+        assert!(excluded(rules, "build"));
+
+        let rules = "*.md\n!README.md\nREADME.md\n";
+        assert!(excluded(rules, "README.md"));
+        assert!(!excluded("*.md\n!README*.md\n", "README-dev.md"));
+        // `*` stays within the root directory.
+        assert!(!excluded("*.md", "docs/guide.md"));
+    }
+
+    #[test]
+    fn keep_overrides_the_rules() {
+        let mut dockerignore = Dockerignore::parse("*\n").unwrap();
+        dockerignore.keep("Dockerfile");
+        dockerignore.keep("docker/[prod].Dockerfile");
+        assert!(!dockerignore.excludes(Path::new("Dockerfile")));
+        assert!(!dockerignore.excludes(Path::new("docker/[prod].Dockerfile")));
+        assert!(dockerignore.excludes(Path::new("docker/p.Dockerfile")));
+        assert!(dockerignore.excludes(Path::new("src")));
+    }
+
+    #[test]
+    fn walks_excluded_directories_with_reincluded_paths() {
+        let dockerignore = Dockerignore::parse("build\n!build/dist/app\n").unwrap();
+        assert!(dockerignore.walks_excluded(Path::new("build")));
+        assert!(dockerignore.walks_excluded(Path::new("build/dist")));
+        assert!(!dockerignore.walks_excluded(Path::new("build/cache")));
+        assert!(!dockerignore.walks_excluded(Path::new("bui")));
+        assert!(
+            !Dockerignore::parse("build\n")
+                .unwrap()
+                .walks_excluded(Path::new("build"))
+        );
+    }
+
+    #[test]
+    fn parse_reports_the_line_of_an_invalid_pattern() {
+        let error = Dockerignore::parse("build\n[a-\n").unwrap_err();
+        assert!(matches!(
+            error.error_type,
+            DockerignoreErrorType::PatternError
+        ));
+        assert!(
+            error.message.starts_with(".dockerignore:2:"),
+            "{}",
+            error.message
+        );
+    }
+}
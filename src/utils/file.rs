@@ -1,24 +1,37 @@
 //! Utility functions for file system operations in the plugin.
 
-use std::io::Error;
-use std::path::Path;
+use std::collections::HashMap;
+use std::fs::FileType;
+use std::io::{Error, ErrorKind, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::thread::JoinHandle;
 
 use bytes::{Bytes, BytesMut};
 
-use tokio::fs::File;
+use futures_util::stream::{Stream, StreamExt};
 
-use tokio_util::codec;
-use tokio_util::codec::{BytesCodec, FramedRead};
+use flate2::read::GzDecoder;
+use nu_glob::Pattern;
+use nu_protocol::ByteStream;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
 
-use futures_util::stream::Map;
-use futures_util::stream::StreamExt;
+/// Number of bytes needed to detect the format of an archive, the size of
+/// a tar header block.
+const MAGIC_SIZE: usize = 512;
+/// Size of the chunks read from a NuShell byte stream.
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Number of chunks buffered while waiting for the daemon to consume them.
+const CHUNK_BUFFER: usize = 16;
 
+#[allow(dead_code)]
 #[derive(Debug)]
 pub enum FileErrorType {
     FileError,
     OtherError,
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
 pub struct FileError {
     pub error_type: FileErrorType,
@@ -35,20 +48,719 @@ pub fn check_file_exists(current_path: &String, path: &str) -> Result<(), FileEr
     Ok(())
 }
 
-pub async fn read_file_stream(
+/// Forward a file chunk by chunk, like `read_byte_stream`.
+///
+/// The stream ends early if reading fails, the returned handle reports
+/// the error once the stream is consumed.
+pub fn read_file_stream(
     current_path: String,
     path: String,
 ) -> Result<
-    Map<FramedRead<File, BytesCodec>, impl FnMut(Result<BytesMut, Error>) -> Bytes>,
+    (
+        impl Stream<Item = Bytes> + Send + 'static,
+        JoinHandle<Result<(), FileError>>,
+    ),
     FileError,
 > {
     let absolute_path = Path::new(&current_path).join(path);
-    let file = File::open(absolute_path).await.map_err(|e| FileError {
+    let file = std::fs::File::open(absolute_path).map_err(|e| FileError {
         error_type: FileErrorType::FileError,
         message: format!("{}", e),
     })?;
+    Ok(forward_reader(file))
+}
+
+/// Forward a NuShell byte stream chunk by chunk, so that at most
+/// `CHUNK_SIZE * CHUNK_BUFFER` bytes are held in memory at a time.
+///
+/// The stream ends early if reading fails, the returned handle reports
+/// the error once the stream is consumed.
+pub fn read_byte_stream(
+    stream: ByteStream,
+) -> Result<
+    (
+        impl Stream<Item = Bytes> + Send + 'static,
+        JoinHandle<Result<(), FileError>>,
+    ),
+    FileError,
+> {
+    let reader = stream.reader().ok_or_else(|| FileError {
+        error_type: FileErrorType::OtherError,
+        message: "The input stream is empty".to_string(),
+    })?;
+    Ok(forward_reader(reader))
+}
+
+/// Read `reader` on a thread, sending its content to the returned stream in
+/// chunks of at most `CHUNK_SIZE` bytes.
+fn forward_reader(
+    mut reader: impl Read + Send + 'static,
+) -> (
+    impl Stream<Item = Bytes> + Send + 'static,
+    JoinHandle<Result<(), FileError>>,
+) {
+    let (sender, receiver) = tokio::sync::mpsc::channel::<Bytes>(CHUNK_BUFFER);
+
+    let handle = std::thread::spawn(move || {
+        let mut buffer = vec![0; CHUNK_SIZE];
+        loop {
+            let size = match reader.read(&mut buffer) {
+                Ok(size) => size,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    return Err(FileError {
+                        error_type: FileErrorType::FileError,
+                        message: format!("{}", e),
+                    });
+                }
+            };
+            if size == 0 {
+                return Ok(());
+            }
+            // The receiver is dropped when the request is aborted.
+            if sender
+                .blocking_send(Bytes::copy_from_slice(&buffer[..size]))
+                .is_err()
+            {
+                return Ok(());
+            }
+        }
+    });
+
+    let stream = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|chunk| (chunk, receiver))
+    });
+    (stream, handle)
+}
+
+/// Sends what is written to the channel in chunks of `CHUNK_SIZE` bytes.
+struct ChannelWriter {
+    sender: tokio::sync::mpsc::Sender<Bytes>,
+    buffer: BytesMut,
+}
+
+impl ChannelWriter {
+    fn send_buffer(&mut self) -> std::io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        // The receiver is dropped when the request is aborted.
+        self.sender
+            .blocking_send(self.buffer.split().freeze())
+            .map_err(|_| Error::new(ErrorKind::BrokenPipe, "The upload was aborted"))
+    }
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        if self.buffer.len() >= CHUNK_SIZE {
+            self.send_buffer()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.send_buffer()
+    }
+}
+
+#[cfg(unix)]
+fn is_socket(file_type: &FileType) -> bool {
+    std::os::unix::fs::FileTypeExt::is_socket(file_type)
+}
+
+#[cfg(not(unix))]
+fn is_socket(_file_type: &FileType) -> bool {
+    false
+}
+
+/// Format a PAX extended header record, `<length> <key>=<value>\n` where the
+/// length counts the whole record, including its own digits.
+fn pax_record(key: &str, value: &[u8]) -> Vec<u8> {
+    let rest = key.len() + value.len() + 3;
+    let mut length = rest;
+    while length != rest + length.to_string().len() {
+        length = rest + length.to_string().len();
+    }
+    let mut record = format!("{} {}=", length, key).into_bytes();
+    record.extend_from_slice(value);
+    record.push(b'\n');
+    record
+}
+
+/// Add the extended attributes of `path` as a PAX header before its entry.
+/// The file systems without extended attributes are silently skipped.
+fn append_xattrs<W: Write>(builder: &mut tar::Builder<W>, path: &Path) -> std::io::Result<()> {
+    let Ok(attributes) = xattr::list(path) else {
+        return Ok(());
+    };
+    let mut records = Vec::new();
+    for attribute in attributes {
+        if let Ok(Some(value)) = xattr::get(path, &attribute) {
+            let key = format!("SCHILY.xattr.{}", attribute.to_string_lossy());
+            records.extend(pax_record(&key, &value));
+        }
+    }
+    if records.is_empty() {
+        return Ok(());
+    }
+    let mut header = tar::Header::new_ustar();
+    header.set_path("././@PaxHeader")?;
+    header.set_entry_type(tar::EntryType::XHeader);
+    header.set_mode(0o644);
+    header.set_size(records.len() as u64);
+    header.set_cksum();
+    builder.append(&header, records.as_slice())
+}
+
+/// Decides which entries are left out of a directory archive.
+pub trait ExcludeRules: Send + 'static {
+    /// Whether the entry at `path`, relative to the root, is left out.
+    fn excludes(&self, path: &Path) -> bool;
+
+    /// Whether the contents of an excluded directory must still be walked,
+    /// for the rules that may bring some of them back.
+    fn walks_excluded(&self, _path: &Path) -> bool {
+        false
+    }
+}
+
+/// Patterns leaving out the entries they match, along with their contents.
+impl ExcludeRules for Vec<Pattern> {
+    fn excludes(&self, path: &Path) -> bool {
+        self.iter().any(|pattern| pattern.matches_path(path))
+    }
+}
+
+/// Call `visit` with the path and the name relative to `root` of every entry
+/// under `root/relative` that is not excluded, parents before their contents.
+fn walk_directory<F>(
+    root: &Path,
+    relative: &Path,
+    exclude: &impl ExcludeRules,
+    visit: &mut F,
+) -> std::io::Result<()>
+where
+    F: FnMut(&Path, &Path, &FileType) -> std::io::Result<()>,
+{
+    let directory = root.join(relative);
+    let mut entries = std::fs::read_dir(&directory)
+        .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| Error::new(e.kind(), format!("{}: {}", directory.display(), e)))?;
+    // Sorted so that the same directory always gives the same archive.
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let name = relative.join(entry.file_name());
+        let path = entry.path();
+        let file_type = entry
+            .file_type()
+            .map_err(|e| Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+        if exclude.excludes(&name) {
+            if file_type.is_dir() && exclude.walks_excluded(&name) {
+                walk_directory(root, &name, exclude, visit)?;
+            }
+            continue;
+        }
+        if is_socket(&file_type) {
+            continue;
+        }
+        visit(&path, &name, &file_type)?;
+        if file_type.is_dir() {
+            walk_directory(root, &name, exclude, visit)?;
+        }
+    }
+    Ok(())
+}
+
+/// Add the contents of `root` to the archive, skipping the excluded entries.
+fn append_directory<W: Write>(
+    builder: &mut tar::Builder<W>,
+    root: &Path,
+    exclude: &impl ExcludeRules,
+) -> std::io::Result<()> {
+    walk_directory(root, Path::new(""), exclude, &mut |path, name, _| {
+        append_xattrs(builder, path)?;
+        builder
+            .append_path_with_name(path, name)
+            .map_err(|e| Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+    })
+}
+
+/// An entry of a directory, as it would be archived.
+#[derive(Debug)]
+pub struct DirectoryEntry {
+    /// The path relative to the root of the directory.
+    pub path: PathBuf,
+    pub file_type: FileType,
+    /// The size of the content, only regular files have one.
+    pub size: u64,
+}
+
+/// List the entries of the directory `root` that `read_directory_stream` or
+/// `read_build_context_stream` would archive.
+pub fn list_directory(
+    root: &Path,
+    exclude: &impl ExcludeRules,
+) -> Result<Vec<DirectoryEntry>, FileError> {
+    let mut entries = Vec::new();
+    walk_directory(
+        root,
+        Path::new(""),
+        exclude,
+        &mut |path, name, file_type| {
+            let size = if file_type.is_file() {
+                std::fs::symlink_metadata(path)
+                    .map_err(|e| Error::new(e.kind(), format!("{}: {}", path.display(), e)))?
+                    .len()
+            } else {
+                0
+            };
+            entries.push(DirectoryEntry {
+                path: name.to_path_buf(),
+                file_type: *file_type,
+                size,
+            });
+            Ok(())
+        },
+    )
+    .map_err(|e| FileError {
+        error_type: FileErrorType::FileError,
+        message: format!("{}", e),
+    })?;
+    Ok(entries)
+}
+
+/// Stream a tar archive of the directory `root`, if any, followed by the
+/// in-memory `files`. See `read_directory_stream`.
+fn archive_stream(
+    root: Option<PathBuf>,
+    exclude: impl ExcludeRules,
+    files: Vec<(String, Vec<u8>)>,
+) -> (
+    impl Stream<Item = Bytes> + Send + 'static,
+    JoinHandle<Result<(), FileError>>,
+) {
+    let (sender, receiver) = tokio::sync::mpsc::channel::<Bytes>(CHUNK_BUFFER);
+
+    let handle = std::thread::spawn(move || {
+        let writer = ChannelWriter {
+            sender,
+            buffer: BytesMut::with_capacity(CHUNK_SIZE),
+        };
+        let mut builder = tar::Builder::new(writer);
+        builder.follow_symlinks(false);
+        let result = root
+            .map_or(Ok(()), |root| {
+                append_directory(&mut builder, &root, &exclude)
+            })
+            .and_then(|_| {
+                files.iter().try_for_each(|(name, content)| {
+                    let mut header = tar::Header::new_gnu();
+                    header.set_mode(0o644);
+                    header.set_size(content.len() as u64);
+                    header.set_cksum();
+                    builder.append_data(&mut header, name, content.as_slice())
+                })
+            })
+            .and_then(|_| builder.into_inner())
+            .and_then(|mut writer| writer.flush());
+        match result {
+            Err(e) if e.kind() == ErrorKind::BrokenPipe => Ok(()),
+            result => result.map_err(|e| FileError {
+                error_type: FileErrorType::FileError,
+                message: format!("{}", e),
+            }),
+        }
+    });
+
+    let stream = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|chunk| (chunk, receiver))
+    });
+    (stream, handle)
+}
+
+/// Stream a tar archive of the directory `root`, built on the fly so that at
+/// most `CHUNK_SIZE * CHUNK_BUFFER` bytes are held in memory at a time.
+///
+/// Modes, owners, modification times, symbolic links and extended attributes
+/// are kept. The entries matching one of the `exclude` patterns, relative to
+/// `root`, are skipped along with their contents.
+///
+/// The stream ends early if archiving fails, the returned handle reports
+/// the error once the stream is consumed.
+pub fn read_directory_stream(
+    root: PathBuf,
+    exclude: Vec<Pattern>,
+) -> (
+    impl Stream<Item = Bytes> + Send + 'static,
+    JoinHandle<Result<(), FileError>>,
+) {
+    archive_stream(Some(root), exclude, Vec::new())
+}
+
+/// Stream the build context of an image, like `read_directory_stream`.
+///
+/// Without `root` the context only holds the Dockerfile. A `dockerfile` given
+/// as `(name, content)` is added at the root of the context under `name`.
+pub fn read_build_context_stream(
+    root: Option<PathBuf>,
+    exclude: impl ExcludeRules,
+    dockerfile: Option<(String, String)>,
+) -> (
+    impl Stream<Item = Bytes> + Send + 'static,
+    JoinHandle<Result<(), FileError>>,
+) {
+    let files = dockerfile
+        .map(|(name, content)| (name, content.into_bytes()))
+        .into_iter()
+        .collect();
+    archive_stream(root, exclude, files)
+}
+
+/// The compression of an archive, detected from its magic bytes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    Uncompressed,
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    /// Detect the compression from the first bytes of an archive, `None` if it
+    /// is neither a tar archive nor compressed with a known format.
+    pub fn detect(header: &[u8]) -> Option<Self> {
+        if header.starts_with(&[0x1f, 0x8b]) {
+            Some(Compression::Gzip)
+        } else if header.starts_with(b"BZh") {
+            Some(Compression::Bzip2)
+        } else if header.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Some(Compression::Xz)
+        } else if header.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Compression::Zstd)
+        } else if is_tar_header(header) {
+            Some(Compression::Uncompressed)
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether `header` starts with the header block of a plain tar archive.
+///
+/// The `ustar` magic is missing from the archives in the old v7 format, so
+/// the block is recognized by its checksum, the sum of its bytes with the
+/// checksum field itself counted as spaces. An archive without any entry is
+/// only made of zeros.
+fn is_tar_header(header: &[u8]) -> bool {
+    let Some(block) = header.get(..MAGIC_SIZE) else {
+        return false;
+    };
+    if block.iter().all(|byte| *byte == 0) {
+        return true;
+    }
+    let field = String::from_utf8_lossy(&block[148..156]);
+    let Ok(checksum) = u32::from_str_radix(field.trim_matches([' ', '\0']), 8) else {
+        return false;
+    };
+    let unsigned: u32 = block
+        .iter()
+        .enumerate()
+        .map(|(i, byte)| {
+            if (148..156).contains(&i) {
+                32
+            } else {
+                *byte as u32
+            }
+        })
+        .sum();
+    // Some old implementations summed the bytes as signed.
+    let signed: i64 = block
+        .iter()
+        .enumerate()
+        .map(|(i, byte)| {
+            if (148..156).contains(&i) {
+                32
+            } else {
+                *byte as i8 as i64
+            }
+        })
+        .sum();
+    checksum == unsigned || checksum as i64 == signed
+}
+
+/// Read the first bytes of a stream to detect the compression of the archive
+/// it carries. The returned stream still yields the whole content.
+pub async fn detect_compression(
+    mut stream: impl Stream<Item = Bytes> + Send + Unpin + 'static,
+) -> (
+    Option<Compression>,
+    impl Stream<Item = Bytes> + Send + Unpin + 'static,
+) {
+    let mut header = BytesMut::new();
+    while header.len() < MAGIC_SIZE {
+        match stream.next().await {
+            Some(chunk) => header.extend_from_slice(&chunk),
+            None => break,
+        }
+    }
+    let header = header.freeze();
+    let compression = Compression::detect(&header);
+    (
+        compression,
+        futures_util::stream::iter(Some(header).filter(|header| !header.is_empty())).chain(stream),
+    )
+}
 
-    Ok(codec::FramedRead::new(file, codec::BytesCodec::new()).map(|r| r.unwrap().freeze()))
+/// Compute the SHA-256 digest of a stream while it is forwarded.
+///
+/// When the digest doesn't match `expected`, the stream ends with an error
+/// instead of ending normally, so that the upload is aborted before the
+/// receiving end sees the whole content. The returned receiver then gets
+/// the mismatch.
+pub fn verify_sha256(
+    stream: impl Stream<Item = Bytes> + Send + Unpin + 'static,
+    expected: String,
+) -> (
+    impl Stream<Item = Result<Bytes, Error>> + Send + 'static,
+    tokio::sync::oneshot::Receiver<FileError>,
+) {
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    let stream =
+        futures_util::stream::unfold(Some((stream, Sha256::new(), sender)), move |state| {
+            let expected = expected.clone();
+            async move {
+                let (mut stream, mut hasher, sender) = state?;
+                if let Some(chunk) = stream.next().await {
+                    hasher.update(&chunk);
+                    return Some((Ok(chunk), Some((stream, hasher, sender))));
+                }
+                let digest = hasher
+                    .finalize()
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect::<String>();
+                if digest == expected {
+                    return None;
+                }
+                let message = format!(
+                    "Checksum mismatch, expected sha256:{} but got sha256:{}",
+                    expected, digest
+                );
+                let _ = sender.send(FileError {
+                    error_type: FileErrorType::OtherError,
+                    message: message.clone(),
+                });
+                Some((Err(Error::new(ErrorKind::InvalidData, message)), None))
+            }
+        });
+    (stream, receiver)
+}
+
+/// Reads the chunks received from the channel, until the sender is dropped.
+struct ChannelReader {
+    receiver: tokio::sync::mpsc::Receiver<Bytes>,
+    chunk: Bytes,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.chunk.is_empty() {
+            match self.receiver.blocking_recv() {
+                Some(chunk) => self.chunk = chunk,
+                None => return Ok(0),
+            }
+        }
+        let size = buf.len().min(self.chunk.len());
+        buf[..size].copy_from_slice(&self.chunk.split_to(size));
+        Ok(size)
+    }
+}
+
+/// An entry of a layer of an image archive.
+#[derive(Debug, Clone)]
+pub struct LayerEntry {
+    /// The absolute path of the entry in the image.
+    pub path: String,
+    pub entry_type: tar::EntryType,
+    pub size: u64,
+    pub mode: u32,
+    /// `user:group`, by name when the layer records it, by id otherwise.
+    pub owner: String,
+}
+
+/// The part of the `manifest.json` of an image archive listing the layers.
+#[derive(Deserialize)]
+struct ArchiveManifest {
+    #[serde(rename = "Layers")]
+    layers: Vec<String>,
+}
+
+/// Resolve the `..` in a path of an image archive.
+fn normalize_archive_path(path: &Path) -> String {
+    let mut parts = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy()),
+            Component::ParentDir => {
+                parts.pop();
+            }
+            _ => {}
+        }
+    }
+    parts.join("/")
+}
+
+fn read_layer_entries<R: Read>(reader: R) -> std::io::Result<Vec<LayerEntry>> {
+    let mut entries = Vec::new();
+    for entry in tar::Archive::new(reader).entries()? {
+        let entry = entry?;
+        let header = entry.header();
+        let path = normalize_archive_path(&entry.path()?);
+        if path.is_empty() {
+            continue;
+        }
+        let name = |name: Option<&str>, id: u64| match name {
+            Some(name) if !name.is_empty() => name.to_string(),
+            _ => id.to_string(),
+        };
+        let user = name(header.username().ok().flatten(), header.uid()?);
+        let group = name(header.groupname().ok().flatten(), header.gid()?);
+        entries.push(LayerEntry {
+            path: format!("/{}", path),
+            entry_type: header.entry_type(),
+            size: entry.size(),
+            mode: header.mode()?,
+            owner: format!("{}:{}", user, group),
+        });
+    }
+    Ok(entries)
+}
+
+/// List the entries of a layer, `None` when the file is not a layer, like
+/// the configuration of the image.
+fn read_layer<R: Read>(mut reader: R) -> std::io::Result<Option<Vec<LayerEntry>>> {
+    let mut header = Vec::with_capacity(MAGIC_SIZE);
+    reader
+        .by_ref()
+        .take(MAGIC_SIZE as u64)
+        .read_to_end(&mut header)?;
+    let reader = std::io::Cursor::new(header.clone()).chain(reader);
+    match Compression::detect(&header) {
+        Some(Compression::Uncompressed) => read_layer_entries(reader).map(Some),
+        Some(Compression::Gzip) => read_layer_entries(GzDecoder::new(reader)).map(Some),
+        Some(compression) => Err(Error::new(
+            ErrorKind::Unsupported,
+            format!("Unsupported layer compression: {:?}", compression),
+        )),
+        None => Ok(None),
+    }
+}
+
+fn read_archive_layers<R: Read>(reader: R) -> std::io::Result<Vec<Vec<LayerEntry>>> {
+    let mut manifest = None;
+    let mut layers = HashMap::new();
+    // `docker save` links the layers shared by several images.
+    let mut links = HashMap::new();
+    for entry in tar::Archive::new(reader).entries()? {
+        let mut entry = entry?;
+        let path = normalize_archive_path(&entry.path()?);
+        match entry.header().entry_type() {
+            tar::EntryType::Symlink | tar::EntryType::Link => {
+                if let Some(target) = entry.link_name()? {
+                    let target = match entry.header().entry_type() {
+                        tar::EntryType::Symlink => Path::new(&path)
+                            .parent()
+                            .unwrap_or(Path::new(""))
+                            .join(target),
+                        _ => target.into_owned(),
+                    };
+                    links.insert(path, normalize_archive_path(&target));
+                }
+            }
+            tar::EntryType::Regular if path == "manifest.json" => {
+                let manifests: Vec<ArchiveManifest> = serde_json::from_reader(&mut entry)
+                    .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+                manifest = manifests.into_iter().next();
+            }
+            tar::EntryType::Regular => {
+                if let Some(entries) = read_layer(&mut entry)? {
+                    layers.insert(path, entries);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let manifest = manifest.ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidData,
+            "The image archive has no manifest.json",
+        )
+    })?;
+    manifest
+        .layers
+        .iter()
+        .map(|layer| {
+            let mut path = normalize_archive_path(Path::new(layer));
+            while let Some(target) = links.get(&path) {
+                if *target == path {
+                    break;
+                }
+                path = target.clone();
+            }
+            layers.get(&path).cloned().ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Layer not found in the image archive: {}", layer),
+                )
+            })
+        })
+        .collect()
+}
+
+/// List the entries of the layers of an image archive, as exported by the
+/// daemon, oldest layer first.
+///
+/// The archive is parsed while it is received, only the listings of the
+/// layers are kept in memory.
+pub async fn read_image_layers<E: std::fmt::Display>(
+    mut stream: impl Stream<Item = Result<Bytes, E>> + Unpin,
+) -> Result<Vec<Vec<LayerEntry>>, FileError> {
+    let (sender, receiver) = tokio::sync::mpsc::channel::<Bytes>(CHUNK_BUFFER);
+    let handle = tokio::task::spawn_blocking(move || {
+        let reader = ChannelReader {
+            receiver,
+            chunk: Bytes::new(),
+        };
+        read_archive_layers(reader)
+    });
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| FileError {
+            error_type: FileErrorType::OtherError,
+            message: format!("{}", e),
+        })?;
+        // The receiver is dropped when parsing fails, the error is reported below.
+        if sender.send(chunk).await.is_err() {
+            break;
+        }
+    }
+    drop(sender);
+
+    handle
+        .await
+        .map_err(|e| FileError {
+            error_type: FileErrorType::OtherError,
+            message: format!("{}", e),
+        })?
+        .map_err(|e| FileError {
+            error_type: FileErrorType::FileError,
+            message: format!("Failed to read the image archive: {}", e),
+        })
 }
 
 impl std::fmt::Display for FileError {
@@ -56,3 +768,113 @@ impl std::fmt::Display for FileError {
         write!(f, "{}", self.message)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An archive with a single file, its header in the given format.
+    fn archive(mut header: tar::Header) -> Vec<u8> {
+        let content = b"hello";
+        header.set_path("hello.txt").unwrap();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_mtime(0);
+        header.set_cksum();
+        let mut builder = tar::Builder::new(Vec::new());
+        builder.append(&header, &content[..]).unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn detect_recognizes_the_compressions() {
+        assert_eq!(
+            Compression::detect(&[0x1f, 0x8b, 0x08, 0x00]),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(Compression::detect(b"BZh91AY"), Some(Compression::Bzip2));
+        assert_eq!(
+            Compression::detect(&[0xfd, b'7', b'z', b'X', b'Z', 0x00, 0x00]),
+            Some(Compression::Xz)
+        );
+        assert_eq!(
+            Compression::detect(&[0x28, 0xb5, 0x2f, 0xfd, 0x00]),
+            Some(Compression::Zstd)
+        );
+    }
+
+    #[test]
+    fn detect_recognizes_plain_tars_without_magic() {
+        let ustar = archive(tar::Header::new_ustar());
+        assert_eq!(&ustar[257..262], b"ustar");
+        assert_eq!(Compression::detect(&ustar), Some(Compression::Uncompressed));
+        let gnu = archive(tar::Header::new_gnu());
+        assert_eq!(Compression::detect(&gnu), Some(Compression::Uncompressed));
+        let v7 = archive(tar::Header::new_old());
+        assert_ne!(&v7[257..262], b"ustar");
+        assert_eq!(Compression::detect(&v7), Some(Compression::Uncompressed));
+        assert_eq!(
+            Compression::detect(&[0; 1024]),
+            Some(Compression::Uncompressed)
+        );
+    }
+
+    #[test]
+    fn detect_rejects_other_content() {
+        let mut json = br#"{"architecture": "amd64", "os": "linux"}"#.to_vec();
+        json.resize(MAGIC_SIZE, b' ');
+        assert_eq!(Compression::detect(&json), None);
+        let mut corrupted = archive(tar::Header::new_old());
+        corrupted[0] = b'x';
+        assert_eq!(Compression::detect(&corrupted), None);
+        assert_eq!(Compression::detect(b"hello"), None);
+    }
+
+    #[test]
+    fn read_layer_lists_v7_archives() {
+        let entries = read_layer(std::io::Cursor::new(archive(tar::Header::new_old())))
+            .unwrap()
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path, "/hello.txt");
+        assert_eq!(entries[0].size, 5);
+        assert!(
+            read_layer(std::io::Cursor::new(vec![0; 1024]))
+                .unwrap()
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn read_file_stream_forwards_the_file() {
+        let dir = std::env::temp_dir().join(format!("ndocker-file-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("image.tar"), vec![7; CHUNK_SIZE + 10]).unwrap();
+
+        let current_path = dir.to_string_lossy().to_string();
+        let (stream, reader) =
+            read_file_stream(current_path.clone(), "image.tar".to_string()).unwrap();
+        let chunks = stream.collect::<Vec<_>>().await;
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(
+            chunks.iter().map(|chunk| chunk.len()).sum::<usize>(),
+            CHUNK_SIZE + 10
+        );
+        assert!(reader.join().unwrap().is_ok());
+
+        assert!(read_file_stream(current_path, "missing.tar".to_string()).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn read_file_stream_reports_read_errors() {
+        // Opening a directory succeeds, reading it fails.
+        let current_path = std::env::temp_dir().to_string_lossy().to_string();
+        let (stream, reader) = read_file_stream(current_path, ".".to_string()).unwrap();
+        assert!(stream.collect::<Vec<_>>().await.is_empty());
+        assert!(reader.join().unwrap().is_err());
+    }
+}
@@ -1,11 +1,13 @@
 //! Utility functions for network operations in the plugin.
 
+#[allow(dead_code)]
 #[derive(Debug)]
 pub enum NetworkErrorType {
     UrlError,
     OtherError,
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
 pub struct NetworkError {
     pub error_type: NetworkErrorType,
@@ -0,0 +1,874 @@
+//! Utility functions for talking to registries through the OCI Distribution
+//! API, without going through the daemon.
+//!
+//! Registries answer anonymous requests with a `401` and a `WWW-Authenticate`
+//! challenge. A `Bearer` challenge names the token server to ask for a token,
+//! with the credentials of the docker config file if there are some; a
+//! `Basic` challenge takes these credentials as they are.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use bollard::auth::DockerCredentials;
+use bytes::Bytes;
+use http_body_util::{BodyExt, Empty};
+use hyper::{Request, Response, StatusCode, header};
+use hyper_rustls::HttpsConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::rt::TokioExecutor;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+
+use crate::utils::auth::{credentials_from_config, registry_host};
+use crate::utils::net::check_url;
+
+/// The host serving the API of Docker Hub.
+const DOCKER_HUB_REGISTRY: &str = "registry-1.docker.io";
+
+pub const OCI_INDEX: &str = "application/vnd.oci.image.index.v1+json";
+pub const OCI_MANIFEST: &str = "application/vnd.oci.image.manifest.v1+json";
+pub const DOCKER_MANIFEST_LIST: &str = "application/vnd.docker.distribution.manifest.list.v2+json";
+pub const DOCKER_MANIFEST: &str = "application/vnd.docker.distribution.manifest.v2+json";
+
+/// The manifest formats asked for, most specific first.
+const MANIFEST_TYPES: &[&str] = &[
+    OCI_INDEX,
+    DOCKER_MANIFEST_LIST,
+    OCI_MANIFEST,
+    DOCKER_MANIFEST,
+];
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub enum RegistryErrorType {
+    InvalidReference,
+    InvalidUrl,
+    Unauthorized,
+    RequestFailed,
+    InvalidManifest,
+}
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct RegistryError {
+    pub error_type: RegistryErrorType,
+    pub message: String,
+}
+
+/// An image reference split into the parts used by the API, e.g.
+/// `docker.io`, `library/alpine` and `3.21` for `alpine:3.21`.
+#[derive(Debug, Clone)]
+pub struct RegistryReference {
+    pub host: String,
+    pub repository: String,
+    /// The tag or the digest, if any.
+    pub reference: Option<String>,
+}
+
+impl RegistryReference {
+    pub fn parse(name: &str) -> Result<Self, RegistryError> {
+        let host = registry_host(name);
+        let rest = name
+            .strip_prefix(&format!("{}/", host))
+            .unwrap_or(name)
+            .to_string();
+        let host = match host.as_str() {
+            "index.docker.io" | DOCKER_HUB_REGISTRY => "docker.io".to_string(),
+            _ => host,
+        };
+
+        let (repository, reference) = match rest.split_once('@') {
+            Some((repository, digest)) => (repository.to_string(), Some(digest.to_string())),
+            None => match rest.rsplit_once(':') {
+                Some((repository, tag)) if !tag.contains('/') => {
+                    (repository.to_string(), Some(tag.to_string()))
+                }
+                _ => (rest, None),
+            },
+        };
+        if repository.is_empty() || reference.as_deref() == Some("") {
+            return Err(RegistryError {
+                error_type: RegistryErrorType::InvalidReference,
+                message: format!("Invalid reference: {}", name),
+            });
+        }
+        // Official images live under `library/` on Docker Hub.
+        let repository = if host == "docker.io" && !repository.contains('/') {
+            format!("library/{}", repository)
+        } else {
+            repository
+        };
+
+        Ok(Self {
+            host,
+            repository,
+            reference,
+        })
+    }
+
+    /// The base URL of the API. Registries on the local machine are spoken
+    /// to over plain HTTP, like the daemon allows by default.
+    pub fn base_url(&self) -> Result<String, RegistryError> {
+        let host = match self.host.as_str() {
+            "docker.io" => DOCKER_HUB_REGISTRY,
+            host => host,
+        };
+        let hostname = match host.strip_prefix('[') {
+            Some(rest) => rest.split(']').next().unwrap_or(rest),
+            None => host.split(':').next().unwrap_or(host),
+        };
+        let scheme = match hostname {
+            "localhost" | "127.0.0.1" | "::1" => "http",
+            _ => "https",
+        };
+        let url = format!("{}://{}", scheme, host);
+        check_url(&url).map_err(|e| RegistryError {
+            error_type: RegistryErrorType::InvalidUrl,
+            message: e.to_string(),
+        })?;
+        Ok(url)
+    }
+}
+
+impl std::fmt::Display for RegistryReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.host, self.repository)?;
+        match &self.reference {
+            Some(reference) if reference.contains(':') => write!(f, "@{}", reference),
+            Some(reference) => write!(f, ":{}", reference),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The platform an image of an index is built for.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Platform {
+    #[serde(default)]
+    pub architecture: String,
+    #[serde(default)]
+    pub os: String,
+    #[serde(default)]
+    pub variant: Option<String>,
+}
+
+impl std::fmt::Display for Platform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.os, self.architecture)?;
+        match &self.variant {
+            Some(variant) => write!(f, "/{}", variant),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A reference to a manifest, a config or a layer.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Descriptor {
+    #[serde(default)]
+    pub media_type: String,
+    pub digest: String,
+    pub size: i64,
+    #[serde(default)]
+    pub platform: Option<Platform>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ManifestContent {
+    #[serde(default)]
+    media_type: Option<String>,
+    #[serde(default)]
+    manifests: Vec<Descriptor>,
+    #[serde(default)]
+    config: Option<Descriptor>,
+    #[serde(default)]
+    layers: Vec<Descriptor>,
+}
+
+/// An image manifest, or an index of the manifests of each platform.
+#[derive(Debug, Clone)]
+pub struct Manifest {
+    pub media_type: String,
+    pub digest: String,
+    pub manifests: Vec<Descriptor>,
+    pub config: Option<Descriptor>,
+    pub layers: Vec<Descriptor>,
+}
+
+impl Manifest {
+    pub fn is_index(&self) -> bool {
+        self.media_type == OCI_INDEX || self.media_type == DOCKER_MANIFEST_LIST
+    }
+
+    /// The compressed size of the image: its config and its layers.
+    pub fn image_size(&self) -> i64 {
+        self.config.iter().map(|config| config.size).sum::<i64>()
+            + self.layers.iter().map(|layer| layer.size).sum::<i64>()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TagList {
+    #[serde(default)]
+    tags: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    #[serde(default)]
+    token: Option<String>,
+    #[serde(default)]
+    access_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    #[serde(default)]
+    errors: Vec<ErrorDetail>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorDetail {
+    #[serde(default)]
+    message: String,
+}
+
+fn request_error(e: impl std::fmt::Display) -> RegistryError {
+    RegistryError {
+        error_type: RegistryErrorType::RequestFailed,
+        message: format!("{}", e),
+    }
+}
+
+/// Turn an error response into a message, using the `errors` of the body
+/// when the registry sends some.
+fn response_error(response: &Response<Bytes>, error_type: RegistryErrorType) -> RegistryError {
+    let status = response.status();
+    let message = serde_json::from_slice::<ErrorResponse>(response.body())
+        .ok()
+        .map(|error| {
+            error
+                .errors
+                .into_iter()
+                .map(|detail| detail.message)
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .filter(|message| !message.is_empty())
+        .unwrap_or_else(|| String::from_utf8_lossy(response.body()).trim().to_string());
+    RegistryError {
+        error_type,
+        message: if message.is_empty() {
+            status.to_string()
+        } else {
+            format!("{}: {}", status, message)
+        },
+    }
+}
+
+/// Split a challenge like `Bearer realm="https://auth.docker.io/token",
+/// service="registry.docker.io"` into its scheme and its parameters.
+fn parse_challenge(challenge: &str) -> (String, HashMap<String, String>) {
+    let (scheme, rest) = challenge
+        .trim()
+        .split_once(' ')
+        .unwrap_or((challenge.trim(), ""));
+    let mut parameters = HashMap::new();
+    let mut chars = rest.chars().peekable();
+    loop {
+        let key = chars
+            .by_ref()
+            .skip_while(|c| *c == ',' || c.is_whitespace())
+            .take_while(|c| *c != '=')
+            .collect::<String>();
+        if key.is_empty() {
+            break;
+        }
+        let mut value = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => value.extend(chars.next()),
+                    '"' => break,
+                    c => value.push(c),
+                }
+            }
+        } else {
+            value = chars.by_ref().take_while(|c| *c != ',').collect();
+        }
+        parameters.insert(key.trim().to_lowercase(), value.trim().to_string());
+    }
+    (scheme.to_lowercase(), parameters)
+}
+
+/// Find the `next` URL of a `Link` header, as sent when the tags are paginated.
+fn next_link(link: &str) -> Option<String> {
+    link.split(',').find_map(|link| {
+        let (url, parameters) = link.split_once(';')?;
+        parameters
+            .split(';')
+            .any(|parameter| parameter.trim().replace(' ', "") == "rel=\"next\"")
+            .then(|| {
+                url.trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string()
+            })
+    })
+}
+
+/// Turn the `next` URL of a `Link` header into a path of the registry at
+/// `base_url`. A URL on another host is dropped, so that the credentials of
+/// the registry are never sent elsewhere.
+fn link_path(base_url: &str, link: &str) -> Option<String> {
+    if link.starts_with('/') && !link.starts_with("//") {
+        return Some(link.to_string());
+    }
+    let (scheme, rest) = link.split_once("://")?;
+    let (authority, path) = rest.split_at(rest.find('/')?);
+    let origin = format!("{}://{}", scheme, authority);
+    origin
+        .eq_ignore_ascii_case(base_url)
+        .then(|| path.to_string())
+}
+
+/// A client for the API of a repository of a registry.
+pub struct RegistryClient {
+    client: Client<HttpsConnector<HttpConnector>, Empty<Bytes>>,
+    base_url: String,
+    repository: String,
+    credentials: Option<DockerCredentials>,
+    /// The `Authorization` header accepted by the registry so far.
+    authorization: Mutex<Option<String>>,
+}
+
+impl RegistryClient {
+    pub fn new(reference: &RegistryReference) -> Result<Self, RegistryError> {
+        let base_url = reference.base_url()?;
+        let credentials = credentials_from_config(&reference.host).map_err(|e| RegistryError {
+            error_type: RegistryErrorType::Unauthorized,
+            message: e.to_string(),
+        })?;
+        let connector = hyper_rustls::HttpsConnectorBuilder::new()
+            .with_native_roots()
+            .map_err(|e| request_error(format!("Failed to load the root certificates: {}", e)))?
+            .https_or_http()
+            .enable_http1()
+            .build();
+        Ok(Self {
+            client: Client::builder(TokioExecutor::new()).build(connector),
+            base_url,
+            repository: reference.repository.clone(),
+            credentials,
+            authorization: Mutex::new(None),
+        })
+    }
+
+    async fn send(
+        &self,
+        url: &str,
+        accept: &[&str],
+        authorization: Option<&str>,
+    ) -> Result<Response<Bytes>, RegistryError> {
+        let mut request = Request::get(url);
+        for media_type in accept {
+            request = request.header(header::ACCEPT, *media_type);
+        }
+        if let Some(authorization) = authorization {
+            request = request.header(header::AUTHORIZATION, authorization);
+        }
+        let request = request.body(Empty::new()).map_err(request_error)?;
+        let response = self
+            .client
+            .request(request)
+            .await
+            .map_err(|e| request_error(format!("{}: {:?}", url, e)))?;
+        let (parts, body) = response.into_parts();
+        let body = body.collect().await.map_err(request_error)?.to_bytes();
+        Ok(Response::from_parts(parts, body))
+    }
+
+    /// Send a `GET` request for a path of the registry, answering the
+    /// challenge of the registry if it asks for one.
+    async fn get(&self, path: &str, accept: &[&str]) -> Result<Response<Bytes>, RegistryError> {
+        let url = format!("{}{}", self.base_url, path);
+        check_url(&url).map_err(|e| RegistryError {
+            error_type: RegistryErrorType::InvalidUrl,
+            message: e.to_string(),
+        })?;
+        let authorization = self.authorization.lock().unwrap().clone();
+        let response = self.send(&url, accept, authorization.as_deref()).await?;
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+
+        let Some(challenge) = response
+            .headers()
+            .get(header::WWW_AUTHENTICATE)
+            .and_then(|challenge| challenge.to_str().ok())
+        else {
+            return Err(response_error(&response, RegistryErrorType::Unauthorized));
+        };
+        let authorization = self.answer_challenge(challenge).await?;
+        let response = self.send(&url, accept, Some(&authorization)).await?;
+        if response.status() == StatusCode::UNAUTHORIZED {
+            return Err(response_error(&response, RegistryErrorType::Unauthorized));
+        }
+        *self.authorization.lock().unwrap() = Some(authorization);
+        Ok(response)
+    }
+
+    fn basic_authorization(&self) -> Option<String> {
+        let credentials = self.credentials.as_ref()?;
+        let username = credentials.username.as_deref()?;
+        let password = credentials.password.as_deref().unwrap_or_default();
+        Some(format!(
+            "Basic {}",
+            STANDARD.encode(format!("{}:{}", username, password))
+        ))
+    }
+
+    /// Get the `Authorization` header answering a `WWW-Authenticate` challenge.
+    async fn answer_challenge(&self, challenge: &str) -> Result<String, RegistryError> {
+        let (scheme, parameters) = parse_challenge(challenge);
+        let unauthorized = || RegistryError {
+            error_type: RegistryErrorType::Unauthorized,
+            message: format!("Unauthorized, no credentials for {}", self.base_url),
+        };
+        if scheme == "basic" {
+            return self.basic_authorization().ok_or_else(unauthorized);
+        }
+        if scheme != "bearer" {
+            return Err(RegistryError {
+                error_type: RegistryErrorType::Unauthorized,
+                message: format!("Unsupported authentication scheme: {}", scheme),
+            });
+        }
+        // A registry token from the config file is used as it is.
+        if let Some(token) = self
+            .credentials
+            .as_ref()
+            .and_then(|credentials| credentials.registrytoken.as_ref())
+        {
+            return Ok(format!("Bearer {}", token));
+        }
+
+        let realm = parameters.get("realm").ok_or_else(unauthorized)?;
+        check_url(realm).map_err(|e| RegistryError {
+            error_type: RegistryErrorType::InvalidUrl,
+            message: e.to_string(),
+        })?;
+        let mut query = Vec::new();
+        if let Some(service) = parameters.get("service") {
+            query.push(("service", service.clone()));
+        }
+        let scope = parameters
+            .get("scope")
+            .cloned()
+            .unwrap_or_else(|| format!("repository:{}:pull", self.repository));
+        query.push(("scope", scope));
+        let query = serde_urlencoded::to_string(&query).map_err(request_error)?;
+        let separator = if realm.contains('?') { '&' } else { '?' };
+        let url = format!("{}{}{}", realm, separator, query);
+
+        let response = self
+            .send(&url, &[], self.basic_authorization().as_deref())
+            .await?;
+        if !response.status().is_success() {
+            return Err(response_error(&response, RegistryErrorType::Unauthorized));
+        }
+        let token: TokenResponse =
+            serde_json::from_slice(response.body()).map_err(|e| RegistryError {
+                error_type: RegistryErrorType::Unauthorized,
+                message: format!("Invalid token response: {}", e),
+            })?;
+        token
+            .token
+            .or(token.access_token)
+            .map(|token| format!("Bearer {}", token))
+            .ok_or_else(|| RegistryError {
+                error_type: RegistryErrorType::Unauthorized,
+                message: "Invalid token response: no token".to_string(),
+            })
+    }
+
+    fn parse_body<T: DeserializeOwned>(response: &Response<Bytes>) -> Result<T, RegistryError> {
+        serde_json::from_slice(response.body()).map_err(|e| RegistryError {
+            error_type: RegistryErrorType::RequestFailed,
+            message: format!("Invalid response: {}", e),
+        })
+    }
+
+    /// List the tags of the repository, following the pages of the registry
+    /// until a page links to no new one.
+    pub async fn tags(&self) -> Result<Vec<String>, RegistryError> {
+        let mut tags = Vec::new();
+        let mut visited = HashSet::new();
+        let mut next = Some(format!("/v2/{}/tags/list", self.repository));
+        while let Some(path) = next.take() {
+            if !visited.insert(path.clone()) {
+                break;
+            }
+            let response = self.get(&path, &[]).await?;
+            if !response.status().is_success() {
+                return Err(response_error(&response, RegistryErrorType::RequestFailed));
+            }
+            let list: TagList = Self::parse_body(&response)?;
+            tags.extend(list.tags.unwrap_or_default());
+            next = response
+                .headers()
+                .get(header::LINK)
+                .and_then(|link| link.to_str().ok())
+                .and_then(next_link)
+                .and_then(|link| link_path(&self.base_url, &link));
+        }
+        Ok(tags)
+    }
+
+    /// Get the manifest of a tag or a digest of the repository.
+    pub async fn manifest(&self, reference: &str) -> Result<Manifest, RegistryError> {
+        let path = format!("/v2/{}/manifests/{}", self.repository, reference);
+        let response = self.get(&path, MANIFEST_TYPES).await?;
+        if !response.status().is_success() {
+            return Err(response_error(
+                &response,
+                RegistryErrorType::InvalidManifest,
+            ));
+        }
+        let content: ManifestContent = Self::parse_body(&response)?;
+
+        let media_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .map(|content_type| content_type.split(';').next().unwrap_or_default().trim())
+            .filter(|content_type| MANIFEST_TYPES.contains(content_type))
+            .map(str::to_string)
+            .or(content.media_type)
+            .unwrap_or_default();
+        if !MANIFEST_TYPES.contains(&media_type.as_str()) {
+            return Err(RegistryError {
+                error_type: RegistryErrorType::InvalidManifest,
+                message: format!("Unsupported manifest type: {}", media_type),
+            });
+        }
+        let digest = response
+            .headers()
+            .get("docker-content-digest")
+            .and_then(|digest| digest.to_str().ok())
+            .map(str::to_string)
+            .unwrap_or_else(|| {
+                let digest = Sha256::digest(response.body())
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect::<String>();
+                format!("sha256:{}", digest)
+            });
+
+        Ok(Manifest {
+            media_type,
+            digest,
+            manifests: content.manifests,
+            config: content.config,
+            layers: content.layers,
+        })
+    }
+}
+
+impl std::fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_server::{Requests, Response, serve_tcp};
+
+    /// A client of the repository `app` of a registry served by `handler`.
+    async fn client(
+        handler: impl Fn(&crate::utils::test_server::Request) -> Response + Send + Sync + 'static,
+    ) -> (RegistryClient, Requests) {
+        let (address, requests) = serve_tcp(handler).await;
+        let reference = RegistryReference::parse(&format!("{}/app", address)).unwrap();
+        (RegistryClient::new(&reference).unwrap(), requests)
+    }
+
+    fn paths(requests: &Requests) -> Vec<String> {
+        requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|request| request.path.clone())
+            .collect()
+    }
+
+    #[test]
+    fn parse_splits_the_reference() {
+        let reference = RegistryReference::parse("alpine:3.21").unwrap();
+        assert_eq!(reference.host, "docker.io");
+        assert_eq!(reference.repository, "library/alpine");
+        assert_eq!(reference.reference.as_deref(), Some("3.21"));
+        assert_eq!(reference.to_string(), "docker.io/library/alpine:3.21");
+        assert_eq!(
+            reference.base_url().unwrap(),
+            "https://registry-1.docker.io"
+        );
+
+        let reference = RegistryReference::parse("index.docker.io/team/app").unwrap();
+        assert_eq!(reference.host, "docker.io");
+        assert_eq!(reference.repository, "team/app");
+        assert_eq!(reference.reference, None);
+
+        let reference = RegistryReference::parse("localhost:5000/team/app:1.0").unwrap();
+        assert_eq!(reference.host, "localhost:5000");
+        assert_eq!(reference.repository, "team/app");
+        assert_eq!(reference.reference.as_deref(), Some("1.0"));
+        assert_eq!(reference.base_url().unwrap(), "http://localhost:5000");
+
+        let reference = RegistryReference::parse("ghcr.io/org/app@sha256:8a1f").unwrap();
+        assert_eq!(reference.host, "ghcr.io");
+        assert_eq!(reference.repository, "org/app");
+        assert_eq!(reference.reference.as_deref(), Some("sha256:8a1f"));
+        assert_eq!(reference.to_string(), "ghcr.io/org/app@sha256:8a1f");
+        assert_eq!(reference.base_url().unwrap(), "https://ghcr.io");
+
+        for invalid in ["alpine:", "ghcr.io/", "app@"] {
+            assert!(RegistryReference::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn parse_challenge_reads_quoted_and_bare_parameters() {
+        let (scheme, parameters) = parse_challenge(
+            r#"Bearer realm="https://auth.docker.io/token",service="registry.docker.io",scope="repository:library/alpine:pull,push""#,
+        );
+        assert_eq!(scheme, "bearer");
+        assert_eq!(parameters["realm"], "https://auth.docker.io/token");
+        assert_eq!(parameters["service"], "registry.docker.io");
+        assert_eq!(parameters["scope"], "repository:library/alpine:pull,push");
+
+        let (scheme, parameters) =
+            parse_challenge(r#"Basic Realm="The \"registry\"", charset=UTF-8"#);
+        assert_eq!(scheme, "basic");
+        assert_eq!(parameters["realm"], "The \"registry\"");
+        assert_eq!(parameters["charset"], "UTF-8");
+
+        let (scheme, parameters) = parse_challenge("Bearer");
+        assert_eq!(scheme, "bearer");
+        assert!(parameters.is_empty());
+    }
+
+    #[test]
+    fn next_link_finds_the_next_page() {
+        assert_eq!(
+            next_link(r#"</v2/app/tags/list?last=b&n=2>; rel="next""#).as_deref(),
+            Some("/v2/app/tags/list?last=b&n=2")
+        );
+        assert_eq!(
+            next_link(r#"<https://r.io/v2/a?last=a>; rel="prev", <https://r.io/v2/a?last=c>; rel = "next""#)
+                .as_deref(),
+            Some("https://r.io/v2/a?last=c")
+        );
+        assert_eq!(next_link(r#"</v2/app/tags/list?last=a>; rel="prev""#), None);
+        assert_eq!(next_link(""), None);
+    }
+
+    #[test]
+    fn link_path_stays_on_the_registry() {
+        let base_url = "https://registry.example.com";
+        assert_eq!(
+            link_path(base_url, "/v2/app/tags/list?last=b").as_deref(),
+            Some("/v2/app/tags/list?last=b")
+        );
+        assert_eq!(
+            link_path(
+                base_url,
+                "https://Registry.example.com/v2/app/tags/list?last=b"
+            )
+            .as_deref(),
+            Some("/v2/app/tags/list?last=b")
+        );
+        for link in [
+            "https://attacker.example.com/v2/app/tags/list",
+            "https://registry.example.com.attacker.example.com/v2/app",
+            "https://registry.example.com:8443/v2/app/tags/list",
+            "http://registry.example.com/v2/app/tags/list",
+            "//attacker.example.com/v2/app/tags/list",
+            "https://registry.example.com",
+            "v2/app/tags/list",
+        ] {
+            assert_eq!(link_path(base_url, link), None, "{link}");
+        }
+    }
+
+    #[tokio::test]
+    async fn tags_follow_the_pages_on_the_registry_only() {
+        let (client, requests) = client(|request| {
+            let host = request.header("host").unwrap_or_default().to_string();
+            match request.path.as_str() {
+                "/v2/app/tags/list" => Response::json(200, r#"{"name": "app", "tags": ["1.0"]}"#)
+                    .header("Link", r#"</v2/app/tags/list?last=1.0>; rel="next""#),
+                "/v2/app/tags/list?last=1.0" => {
+                    Response::json(200, r#"{"name": "app", "tags": ["1.1"]}"#).header(
+                        "Link",
+                        &format!(r#"<http://{host}/v2/app/tags/list?last=1.1>; rel="next""#),
+                    )
+                }
+                "/v2/app/tags/list?last=1.1" => {
+                    Response::json(200, r#"{"name": "app", "tags": ["2.0"]}"#).header(
+                        "Link",
+                        r#"<http://attacker.example.com/v2/app/tags/list?last=2.0>; rel="next""#,
+                    )
+                }
+                _ => Response::json(404, r#"{"errors": [{"message": "unknown"}]}"#),
+            }
+        })
+        .await;
+
+        assert_eq!(client.tags().await.unwrap(), vec!["1.0", "1.1", "2.0"]);
+        assert_eq!(
+            paths(&requests),
+            vec![
+                "/v2/app/tags/list",
+                "/v2/app/tags/list?last=1.0",
+                "/v2/app/tags/list?last=1.1",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn tags_stop_on_a_page_seen_before() {
+        let (client, requests) = client(|request| {
+            let (tags, next) = match request.path.as_str() {
+                "/v2/app/tags/list" => ("1.0", "/v2/app/tags/list?last=1.0"),
+                _ => ("1.1", "/v2/app/tags/list"),
+            };
+            Response::json(200, &format!(r#"{{"tags": ["{tags}"]}}"#))
+                .header("Link", &format!(r#"<{next}>; rel="next""#))
+        })
+        .await;
+
+        assert_eq!(client.tags().await.unwrap(), vec!["1.0", "1.1"]);
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn get_answers_the_bearer_challenge_once() {
+        let (client, requests) = client(|request| {
+            let host = request.header("host").unwrap_or_default().to_string();
+            if request.path.starts_with("/token?") {
+                return Response::json(200, r#"{"token": "abc"}"#);
+            }
+            if request.header("authorization") != Some("Bearer abc") {
+                return Response::json(
+                    401,
+                    r#"{"errors": [{"message": "authentication required"}]}"#,
+                )
+                .header(
+                    "WWW-Authenticate",
+                    &format!(r#"Bearer realm="http://{host}/token",service="test""#),
+                );
+            }
+            match request.path.as_str() {
+                "/v2/app/tags/list" => Response::json(200, r#"{"tags": ["1.0"]}"#)
+                    .header("Link", r#"</v2/app/tags/list?last=1.0>; rel="next""#),
+                _ => Response::json(200, r#"{"tags": null}"#),
+            }
+        })
+        .await;
+
+        assert_eq!(client.tags().await.unwrap(), vec!["1.0"]);
+        assert_eq!(
+            paths(&requests),
+            vec![
+                "/v2/app/tags/list",
+                "/token?service=test&scope=repository%3Aapp%3Apull",
+                "/v2/app/tags/list",
+                "/v2/app/tags/list?last=1.0",
+            ]
+        );
+        assert!(
+            requests
+                .lock()
+                .unwrap()
+                .iter()
+                .all(|request| request.method == "GET")
+        );
+    }
+
+    #[tokio::test]
+    async fn manifest_reads_an_index_and_its_manifests() {
+        const IMAGE: &str = r#"{"schemaVersion": 2,
+            "config": {"mediaType": "application/vnd.oci.image.config.v1+json", "digest": "sha256:c0", "size": 1000},
+            "layers": [{"mediaType": "application/vnd.oci.image.layer.v1.tar+gzip", "digest": "sha256:l0", "size": 3000000}]}"#;
+        let (client, requests) = client(|request| match request.path.as_str() {
+            "/v2/app/manifests/latest" => Response::json(
+                200,
+                r#"{"schemaVersion": 2, "manifests": [
+                    {"mediaType": "application/vnd.docker.distribution.manifest.v2+json", "digest": "sha256:amd64", "size": 528,
+                     "platform": {"architecture": "amd64", "os": "linux"}},
+                    {"mediaType": "application/vnd.docker.distribution.manifest.v2+json", "digest": "sha256:arm64", "size": 528,
+                     "platform": {"architecture": "arm64", "os": "linux", "variant": "v8"}}]}"#,
+            )
+            .header("Content-Type", &format!("{}; charset=utf-8", DOCKER_MANIFEST_LIST))
+            .header("Docker-Content-Digest", "sha256:index"),
+            "/v2/app/manifests/sha256:amd64" => {
+                Response::json(200, IMAGE).header("Content-Type", OCI_MANIFEST)
+            }
+            "/v2/app/manifests/sha256:html" => {
+                Response::json(200, "{}").header("Content-Type", "text/html")
+            }
+            _ => Response::json(404, r#"{"errors": [{"message": "manifest unknown"}]}"#),
+        })
+        .await;
+
+        let index = client.manifest("latest").await.unwrap();
+        assert!(index.is_index());
+        assert_eq!(index.media_type, DOCKER_MANIFEST_LIST);
+        assert_eq!(index.digest, "sha256:index");
+        let platforms = index
+            .manifests
+            .iter()
+            .map(|descriptor| descriptor.platform.clone().unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(platforms, vec!["linux/amd64", "linux/arm64/v8"]);
+        let accept = requests.lock().unwrap()[0]
+            .headers
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("accept"))
+            .map(|(_, value)| value.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(accept, MANIFEST_TYPES);
+
+        let image = client.manifest("sha256:amd64").await.unwrap();
+        assert!(!image.is_index());
+        assert_eq!(image.image_size(), 3001000);
+        // The digest is the one of the body when the registry doesn't say.
+        let digest = Sha256::digest(IMAGE.as_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+        assert_eq!(image.digest, format!("sha256:{}", digest));
+
+        let error = client.manifest("sha256:html").await.unwrap_err();
+        assert!(matches!(
+            error.error_type,
+            RegistryErrorType::InvalidManifest
+        ));
+        let error = client.manifest("missing").await.unwrap_err();
+        assert!(
+            error.message.contains("manifest unknown"),
+            "{}",
+            error.message
+        );
+    }
+}